@@ -0,0 +1,71 @@
+use yew::prelude::*;
+use yew_agent::{Bridge, Bridged};
+
+use crate::services::event_bus::{Event, EventBus};
+use crate::services::websocket::ConnectionState;
+
+/// Off-screen live region telling screen reader users when the connection
+/// to the server drops and when it comes back, as reported on the
+/// [`EventBus`].
+pub struct ConnectionAnnouncer {
+    text: String,
+    /// Whether the connection dropped since it was last up.
+    lost: bool,
+    _bus: Box<dyn Bridge<EventBus>>,
+}
+
+impl ConnectionAnnouncer {
+    fn announcement(&mut self, state: &ConnectionState) -> Option<String> {
+        match state {
+            // Only the first attempt; counting every retry would be noise.
+            ConnectionState::Reconnecting { attempt: 1, .. } => {
+                self.lost = true;
+                Some("Disconnected. Reconnecting…".to_string())
+            }
+            ConnectionState::Disconnected(close) => {
+                self.lost = true;
+                Some(match close {
+                    Some(close) => format!("Disconnected. {}", close.describe()),
+                    None => "Disconnected from the chat server.".to_string(),
+                })
+            }
+            ConnectionState::Connected if self.lost => {
+                self.lost = false;
+                Some("Reconnected.".to_string())
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Component for ConnectionAnnouncer {
+    type Message = ConnectionState;
+    type Properties = ();
+
+    fn create(ctx: &Context<Self>) -> Self {
+        Self {
+            text: String::new(),
+            lost: false,
+            _bus: EventBus::bridge(ctx.link().batch_callback(|event| match event {
+                Event::Connection(state) => Some(state),
+                _ => None,
+            })),
+        }
+    }
+
+    fn update(&mut self, _ctx: &Context<Self>, state: Self::Message) -> bool {
+        match self.announcement(&state) {
+            Some(text) => {
+                self.text = text;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn view(&self, _ctx: &Context<Self>) -> Html {
+        html! {
+            <div aria-live="assertive" aria-atomic="true" class="sr-only">{&self.text}</div>
+        }
+    }
+}
@@ -0,0 +1,72 @@
+use gloo::events::EventListener;
+use wasm_bindgen::JsCast;
+use web_sys::KeyboardEvent;
+use yew::prelude::*;
+
+use crate::components::context_menu::MenuItem;
+
+#[derive(Properties, PartialEq)]
+pub struct AttachMenuProps {
+    /// One entry per kind of attachment that can be sent.
+    pub items: Vec<MenuItem>,
+}
+
+/// "+" button in the composer opening a menu of the things that can be sent
+/// besides text. Selecting an item, clicking outside or pressing Escape closes
+/// it.
+#[function_component(AttachMenu)]
+pub fn attach_menu(props: &AttachMenuProps) -> Html {
+    let open = use_state(|| false);
+    {
+        let is_open = *open;
+        let open = open.clone();
+        use_effect_with_deps(
+            move |is_open| {
+                let listener = is_open.then(|| {
+                    EventListener::new(&gloo::utils::window(), "keydown", move |e| {
+                        let escape = e
+                            .dyn_ref::<KeyboardEvent>()
+                            .is_some_and(|e| e.key() == "Escape");
+                        if escape {
+                            open.set(false);
+                        }
+                    })
+                });
+                move || drop(listener)
+            },
+            is_open,
+        );
+    }
+    let toggle = {
+        let open = open.clone();
+        Callback::from(move |_: MouseEvent| open.set(!*open))
+    };
+    let close = {
+        let open = open.clone();
+        Callback::from(move |_: MouseEvent| open.set(false))
+    };
+
+    html! {
+        <div class="relative mr-2">
+            <button onclick={toggle} title="Attach" aria-haspopup="menu" aria-expanded={open.to_string()} class="w-8 h-8 rounded-full border-2 border-gray-300 text-lg leading-none font-bold text-gray-500 hover:text-gray-800">{"+"}</button>
+            if *open {
+                <div onclick={close} class="fixed inset-0 z-40"></div>
+                <ul role="menu" class="absolute bottom-full left-0 mb-2 z-50 min-w-32 bg-white rounded-lg shadow-lg py-1 text-sm">
+                    {for props.items.iter().map(|item| {
+                        let select = {
+                            let on_select = item.on_select.clone();
+                            let open = open.clone();
+                            Callback::from(move |_: MouseEvent| {
+                                open.set(false);
+                                on_select.emit(());
+                            })
+                        };
+                        html! {
+                            <li key={item.label} role="menuitem" onclick={select} class="px-4 py-1 cursor-pointer hover:bg-gray-100">{item.label}</li>
+                        }
+                    })}
+                </ul>
+            }
+        </div>
+    }
+}
@@ -0,0 +1,72 @@
+use serde_json::Value;
+use yew::prelude::*;
+
+/// Renders structured message metadata posted by bots and integrations, or
+/// `None` when the shape isn't known, in which case the caller shows the
+/// message text instead. Known shapes, selected by `type`:
+///
+/// - `{"type": "card", "title": "…", "body": "…", "buttons": [{"label": "…", "url": "https://…"}]}`
+/// - `{"type": "fields", "title": "…", "fields": [{"name": "…", "value": "…"}]}`
+///
+/// Everything but the title is optional. Button URLs other than `http(s)`
+/// are dropped.
+pub fn render_card(value: &Value) -> Option<Html> {
+    let title = value.get("title")?.as_str()?;
+    match value.get("type")?.as_str()? {
+        "card" => Some(render_basic_card(title, value)),
+        "fields" => Some(render_fields_card(title, value)),
+        _ => None,
+    }
+}
+
+fn render_basic_card(title: &str, value: &Value) -> Html {
+    let body = value.get("body").and_then(Value::as_str);
+    let buttons = array(value, "buttons").filter_map(|button| {
+        let label = button.get("label")?.as_str()?;
+        let url = button.get("url")?.as_str()?;
+        is_web_url(url).then_some((label, url))
+    });
+    html! {
+        <div class="border-l-4 border-blue-500 pl-3 my-1">
+            <p class="font-bold text-gray-800 text-sm">{title}</p>
+            if let Some(body) = body {
+                <p class="whitespace-pre-wrap mt-1">{body}</p>
+            }
+            <div class="flex flex-wrap mt-2">
+                {for buttons.map(|(label, url)| html! {
+                    <a href={url.to_string()} target="_blank" rel="noopener noreferrer" class="mr-2 mb-1 px-3 py-1 rounded border border-blue-600 text-blue-600 hover:bg-blue-50">{label}</a>
+                })}
+            </div>
+        </div>
+    }
+}
+
+fn render_fields_card(title: &str, value: &Value) -> Html {
+    let fields = array(value, "fields")
+        .filter_map(|field| Some((field.get("name")?.as_str()?, field.get("value")?.as_str()?)));
+    html! {
+        <div class="border-l-4 border-gray-400 pl-3 my-1">
+            <p class="font-bold text-gray-800 text-sm mb-1">{title}</p>
+            <dl class="grid grid-cols-2 gap-x-4 gap-y-1">
+                {for fields.map(|(name, value)| html! {
+                    <>
+                        <dt class="font-medium text-gray-500">{name}</dt>
+                        <dd>{value}</dd>
+                    </>
+                })}
+            </dl>
+        </div>
+    }
+}
+
+fn array<'a>(value: &'a Value, key: &str) -> impl Iterator<Item = &'a Value> {
+    value
+        .get(key)
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+}
+
+fn is_web_url(url: &str) -> bool {
+    url.starts_with("https://") || url.starts_with("http://")
+}
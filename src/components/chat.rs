@@ -1,190 +1,3692 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::hash::{Hash, Hasher};
+
+use gloo::events::EventListener;
+use gloo::render::{request_animation_frame, AnimationFrame};
+use gloo::storage::{LocalStorage, Storage};
+use gloo::timers::callback::{Interval, Timeout};
 use serde::{Deserialize, Serialize};
-use web_sys::HtmlInputElement;
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{
+    HtmlInputElement, HtmlTextAreaElement, KeyboardEvent, ScrollBehavior, ScrollIntoViewOptions,
+    ScrollLogicalPosition,
+};
 use yew::prelude::*;
 use yew_agent::{Bridge, Bridged};
+use yew_router::prelude::*;
 
-use crate::services::event_bus::EventBus;
-use crate::{services::websocket::WebsocketService, User};
+use crate::components::attach_menu::AttachMenu;
+use crate::components::context_menu::{ContextMenu, MenuItem};
+use crate::components::gif_picker::GifPicker;
+use crate::components::lightbox::Lightbox;
+use crate::components::markup::{
+    fence_text, quote_text, render_text, utf16_to_byte, wrap_selection, Format,
+};
+use crate::components::message_item::{message_dom_id, MessageItem};
+use crate::components::modal::Modal;
+use crate::components::poll::{PollDialog, PollTally, MAX_OPTIONS};
+use crate::components::report::{ReportDialog, ReportReason};
+use crate::components::settings::Settings;
+use crate::components::shortcuts::{is_typing_target, ShortcutHelp};
+use crate::services::auth;
+use crate::services::avatar::{avatar_url, DEFAULT_AVATAR_SET};
+use crate::services::clipboard::copy_to_clipboard;
+use crate::services::event_bus::{Event, EventBus};
+use crate::services::gif;
+use crate::services::logging::{
+    log_duplicate_dropped, log_send_error, log_sends_expired, log_sequence_gap, log_unknown_frame,
+    log_unparsable_frame,
+};
+use crate::services::long_poll::LongPollService;
+use crate::services::preferences::{Preferences, ScrollOnLoad, TimeFormat, TimeOfDay};
+use crate::services::sound::play_notification;
+use crate::services::toast::{show_toast, ToastLevel};
+use crate::services::transport::Transport;
+use crate::services::websocket::{
+    CloseReason, ConnectionQuality, ConnectionState, MsgTypes, WebSocketMessage, WebsocketService,
+    WsError, PROTOCOL_VERSION,
+};
+use crate::{Route, User};
 
+#[allow(clippy::enum_variant_names)]
 pub enum Msg {
     HandleMsg(String),
+    ConnectionChanged(ConnectionState),
+    Reconnect,
+    RejoinAvailable,
+    Logout,
     SubmitMessage,
+    /// Sends the whole draft as a fenced code block.
+    SendAsCode,
+    ConfirmLargeSend,
+    CancelSend,
+    DismissSendError,
+    ResendFailed,
+    ExpirePending,
+    ToggleGifPicker,
+    Quote(String),
+    ApplyFormat(Format),
+    TogglePin(String),
+    TogglePinnedPanel,
+    ToggleStats,
+    InputChanged(String),
+    TypingExpired(String),
+    PulseEnded(String),
+    FlushRoster,
+    FlushInbound,
+    MoveMentionSelection(isize),
+    CompleteMention,
+    DismissMention,
+    SendGif(String),
+    HashChanged,
+    ViewportChanged,
+    /// The page is being left; remember where the message list was.
+    SaveScrollPosition,
+    CopyInviteLink,
+    JumpToMessage(String),
+    JumpToUser(String),
+    ToggleAllUsers,
+    SetRosterSort(RosterSort),
+    FilterRoster(String),
+    RefreshRoster,
+    ToggleUnreadOnly,
+    WindowFocus(bool),
+    Activity,
+    IdleTimeout,
+    LogoutTick,
+    StayLoggedIn,
+    ToggleSettings,
+    ToggleShortcutHelp,
+    /// Moves that many rooms down the sidebar (up if negative), wrapping
+    /// around, optionally only stopping at rooms with unread messages.
+    CycleRoom(isize, bool),
+    ToggleMinimize,
+    ToggleFocusMode,
+    ToggleCompactRoster,
+    ToggleSidebar,
+    OpenLightbox(String),
+    OpenContextMenu(String, i32, i32),
+    ToggleExpanded(String),
+    CloseContextMenu,
+    ShowDetails(String),
+    CloseDetails,
+    CopyMessage(String),
+    CopyMessageLink(String),
+    OpenReport(String),
+    /// Asks which room to forward the message with this id to.
+    OpenForward(String),
+    CloseForward,
+    /// Forwards the message the dialog is open for to this room.
+    Forward(String),
+    TogglePollDialog,
+    CreatePoll(String, Vec<String>),
+    /// Poll id and option index; voting for our current choice takes it back.
+    Vote(String, usize),
+    /// Shows the thread under the message with this id in the side panel.
+    OpenThread(String),
+    CloseThread,
+    ThreadInputChanged(String),
+    SubmitThreadReply,
+    CloseReport,
+    SubmitReport(ReportReason),
+    CloseLightbox,
+    UpdatePreferences(Preferences),
+}
+
+/// Messages longer than this many characters need confirming before sending.
+const LARGE_MESSAGE_CHARS: usize = 1000;
+
+/// Seconds the inactivity warning is shown before logging out.
+const LOGOUT_WARNING_SECS: u32 = 60;
+
+/// `localStorage` key remembering whether the chat was left minimized.
+const MINIMIZED_KEY: &str = "yewchat.minimized";
+
+/// `localStorage` key remembering whether focus mode hid the sidebar.
+const FOCUS_MODE_KEY: &str = "yewchat.focus-mode";
+
+/// `localStorage` key remembering whether the roster drops avatars.
+const COMPACT_ROSTER_KEY: &str = "yewchat.compact-roster";
+
+/// `localStorage` key holding the room and message list scroll offset when
+/// the page was left.
+const SCROLL_POSITION_KEY: &str = "yewchat.scroll-position";
+
+/// Viewports matching this start in focus mode unless the user chose
+/// otherwise before.
+const NARROW_VIEWPORT_QUERY: &str = "(max-width: 768px)";
+
+/// Users matching this asked their OS for less motion. CSS animations use
+/// Tailwind's `motion-reduce:` variants; scrolling done from code checks it.
+const REDUCED_MOTION_QUERY: &str = "(prefers-reduced-motion: reduce)";
+
+/// Prefix of ids generated for messages the server sent without one.
+const LOCAL_ID_PREFIX: &str = "local-";
+
+/// Comma-separated user ids allowed to pin messages, read at build time from
+/// `PIN_ALLOWED_USERS`. Anyone can pin when it isn't set.
+const PIN_ALLOWED_USERS: Option<&str> = option_env!("PIN_ALLOWED_USERS");
+
+/// Room every message without an explicit room belongs to.
+const DEFAULT_ROOM: &str = "general";
+
+/// Sending the same text again while the first copy is still unacknowledged
+/// is ignored for this long, to absorb double clicks on a slow connection.
+const DUPLICATE_SEND_WINDOW_MS: f64 = 5_000.0;
+
+/// Unacknowledged messages from which the composer shows how many are
+/// waiting for the server.
+const BACKLOG_NOTICE: usize = 5;
+/// Unacknowledged messages at which sending pauses until the server catches
+/// up, rather than piling up more.
+const MAX_BACKLOG: usize = 20;
+/// Messages unacknowledged for longer than this are presumed lost and move to
+/// the failed ones, so a dropped echo can't block sending and can be retried.
+const BACKLOG_WINDOW_MS: f64 = 60_000.0;
+/// How often pending messages are checked against [`BACKLOG_WINDOW_MS`].
+const PENDING_SWEEP_MS: u32 = 5_000;
+
+/// How long after being removed by the server the rejoin button unlocks.
+const REJOIN_DELAY_MS: u32 = 30_000;
+
+/// Slash commands answered by the client, as listed by `/help`.
+const COMMANDS: &[(&str, &str)] = &[
+    ("help", "List the available commands"),
+    ("template <name>", "Put a saved template in the composer"),
+    (
+        "whisper <message>",
+        "Send a message that isn't kept in history",
+    ),
+];
+
+/// Sender shown on replies to slash commands.
+const SYSTEM_SENDER: &str = "yewchat";
+
+/// How long a message jumped to stays highlighted; matches the `flash`
+/// animation in `index.html`.
+const HIGHLIGHT_MS: u32 = 1_500;
+
+/// Recent round trips and sends kept to judge the connection quality.
+const QUALITY_SAMPLES: usize = 20;
+
+/// How long a roster entry stays highlighted after its user posts.
+const POST_PULSE_MS: u32 = 1_000;
+
+/// Roster and presence updates arriving within this long of the first one
+/// are applied together, so busy rooms don't re-render on every frame.
+const ROSTER_BATCH_MS: u32 = 250;
+
+/// Most usernames suggested after `@`.
+const MAX_MENTION_SUGGESTIONS: usize = 5;
+
+/// Minimum interval between `typing` frames while the user keeps typing.
+const TYPING_THROTTLE_MS: f64 = 3_000.0;
+
+/// How long someone is shown as typing after their last `typing` frame.
+const TYPING_TIMEOUT_MS: u32 = 5_000;
+
+/// How many message keys are remembered to drop repeats. Replays only cover
+/// recent history, so older keys are forgotten to keep memory bounded.
+const MAX_SEEN_KEYS: usize = 2_000;
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct MessageData {
+    pub from: String,
+    pub message: String,
+    id: Option<String>,
+    /// Milliseconds since the epoch.
+    timestamp: Option<i64>,
+    room: Option<String>,
+    /// Sender's user id; servers that don't assign ids leave it out.
+    user_id: Option<String>,
+    /// Structured content from bots and integrations, rendered by
+    /// `render_card` in place of the text when its shape is known.
+    pub metadata: Option<serde_json::Value>,
+    /// Sent by a bot or integration rather than a person. Servers set it on
+    /// messages from their bots; replies to slash commands have it too.
+    #[serde(default)]
+    pub bot: bool,
+    /// Relayed live but not kept in history, so it's gone after a reload.
+    #[serde(default)]
+    pub ephemeral: bool,
+    /// Order in which the server accepted the message, counting up by one.
+    /// Numbered messages are listed in this order however they arrive.
+    seq: Option<u64>,
+    /// Id of the thread root this replies to. Replies are shown in the
+    /// thread panel rather than the room.
+    reply_to: Option<String>,
+    /// Name of who originally sent a message forwarded from another room.
+    pub forwarded_from: Option<String>,
+    /// When the text was last changed, in milliseconds since the epoch.
+    edited_at: Option<i64>,
+    /// Earlier versions of the text, oldest first, each with the time it was
+    /// written. Kept from the edits we see unless the server sends it.
+    #[serde(default)]
+    pub edit_history: Vec<(String, i64)>,
+}
+
+impl MessageData {
+    fn room(&self) -> &str {
+        self.room.as_deref().unwrap_or(DEFAULT_ROOM)
+    }
+
+    /// Sender's user id, falling back to the display name.
+    fn user_id(&self) -> &str {
+        self.user_id.as_deref().unwrap_or(&self.from)
+    }
+
+    /// Milliseconds since the epoch; set on receipt for servers that don't
+    /// stamp messages.
+    pub fn timestamp(&self) -> Option<i64> {
+        self.timestamp
+    }
+
+    /// Server id, or the client-generated one assigned on receipt.
+    pub fn id(&self) -> &str {
+        self.id.as_deref().unwrap_or_default()
+    }
+
+    /// Whether the id was generated by this client, in which case it means
+    /// nothing to other clients.
+    pub fn has_local_id(&self) -> bool {
+        self.id().starts_with(LOCAL_ID_PREFIX)
+    }
+
+    /// Whether the text was changed after it was sent.
+    pub fn edited(&self) -> bool {
+        self.edited_at.is_some() || !self.edit_history.is_empty()
+    }
+
+    /// Replaces the text with `text`, written at `at`, keeping the current
+    /// text in the history. Returns whether the text changed.
+    fn apply_edit(&mut self, text: String, at: i64) -> bool {
+        if text == self.message {
+            return false;
+        }
+        let written = self.edited_at.or(self.timestamp).unwrap_or(at);
+        let previous = std::mem::replace(&mut self.message, text);
+        self.edit_history.push((previous, written));
+        self.edited_at = Some(at);
+        true
+    }
+}
+
+/// Roster entry sent as JSON in the `data` of a `users` frame by servers that
+/// track rooms, e.g. `[{"name": "alice", "room": "general"}]`. Servers without
+/// rooms only send the names in `dataArray`.
+#[derive(Default, Deserialize)]
+struct RosterEntry {
+    /// Stable user id; display names may repeat. Defaults to the name.
+    id: Option<String>,
+    name: String,
+    room: Option<String>,
+    #[serde(default)]
+    status: Status,
+    /// Robohash set the user picked; see `MsgTypes::Profile`.
+    avatar_set: Option<u8>,
+    #[serde(default)]
+    role: Role,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Status {
+    #[default]
+    Online,
+    Away,
+}
+
+/// What a user may do beyond chatting.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Role {
+    #[default]
+    Member,
+    /// May post in read-only rooms.
+    Admin,
+}
+
+impl Status {
+    fn as_str(self) -> &'static str {
+        match self {
+            Status::Online => "online",
+            Status::Away => "away",
+        }
+    }
 }
 
+/// Payload of an inbound `presence` frame. Clients send just the status as
+/// `data`; the server relays it to everyone with the sender attached, e.g.
+/// `{"from": "alice", "status": "away"}`.
 #[derive(Deserialize)]
-struct MessageData {
+struct PresenceData {
     from: String,
-    message: String,
+    user_id: Option<String>,
+    status: Status,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
-#[serde(rename_all = "lowercase")]
-pub enum MsgTypes {
-    Users,
-    Register,
-    Message,
+/// Payload of an inbound `typing` frame, relayed by the server with the
+/// sender attached, e.g. `{"from": "alice"}`. The frame's `room` says where.
+#[derive(Deserialize)]
+struct TypingData {
+    from: String,
+    user_id: Option<String>,
+}
+
+/// Payload of an outbound `report` frame, e.g.
+/// `{"message_id": "42", "reason": "spam"}`.
+#[derive(Serialize)]
+struct ReportData<'a> {
+    message_id: &'a str,
+    reason: ReportReason,
+}
+
+/// Payload of an outbound `poll` frame.
+#[derive(Serialize)]
+struct PollData<'a> {
+    question: &'a str,
+    options: &'a [String],
 }
 
+/// Payload of a `vote` frame, e.g. `{"poll_id": "42", "option": 1}`. Relayed
+/// votes carry the voter's `user_id` too.
 #[derive(Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct WebSocketMessage {
-    message_type: MsgTypes,
-    data_array: Option<Vec<String>>,
-    data: Option<String>,
+struct VoteData {
+    poll_id: String,
+    option: Option<usize>,
+    #[serde(default, skip_serializing)]
+    user_id: Option<String>,
+}
+
+/// Payload of an `edit` frame.
+#[derive(Deserialize)]
+struct EditData {
+    message_id: String,
+    message: String,
+    edited_at: Option<i64>,
+}
+
+/// Payload of a `room` frame, e.g. `{"read_only": true}`.
+#[derive(Deserialize)]
+struct RoomData {
+    #[serde(default)]
+    read_only: bool,
+}
+
+/// A message we sent that the server hasn't echoed back yet, or one that
+/// couldn't be sent at all.
+struct PendingMessage {
+    text: String,
+    room: String,
+    ephemeral: bool,
+    /// Thread root, for a reply sent from the thread panel.
+    reply_to: Option<String>,
+    /// Original sender, for a forwarded message.
+    forwarded_from: Option<String>,
+    /// Milliseconds since the epoch.
+    sent_at: f64,
+}
+
+impl PendingMessage {
+    fn frame(&self) -> WebSocketMessage {
+        WebSocketMessage {
+            message_type: MsgTypes::Message,
+            data: Some(self.text.clone()),
+            data_array: None,
+            room: Some(self.room.clone()),
+            version: PROTOCOL_VERSION,
+            ephemeral: self.ephemeral,
+            reply_to: self.reply_to.clone(),
+            forwarded_from: self.forwarded_from.clone(),
+        }
+    }
+}
+
+/// Someone currently typing; they stop showing when `_expiry` fires.
+struct Typer {
+    name: String,
+    room: String,
+    _expiry: Timeout,
 }
 
 #[derive(Clone)]
 struct UserProfile {
+    id: String,
     name: String,
     avatar: String,
+    /// Room the user is in; `None` when the server doesn't report rooms.
+    room: Option<String>,
+    status: Status,
+    role: Role,
+    /// When the user last sent a message or typed, in milliseconds since the
+    /// epoch; `0.0` if not seen yet.
+    last_active: f64,
+}
+
+/// Order of the roster in the sidebar.
+#[derive(Clone, Copy, PartialEq)]
+pub enum RosterSort {
+    Alphabetical,
+    RecentActivity,
+}
+
+impl UserProfile {
+    fn in_room(&self, room: &str) -> bool {
+        self.room.as_deref().is_none_or(|r| r == room)
+    }
 }
 
 pub struct Chat {
+    username: String,
+    /// Our id as assigned by the server in reply to `register`; the username
+    /// until then.
+    user_id: String,
     users: Vec<UserProfile>,
+    /// Whether the first roster has arrived; placeholders are shown until then.
+    initial_loaded: bool,
     chat_input: NodeRef,
+    message_list: NodeRef,
+    /// Scroll the message list to the bottom after the next render, so our
+    /// own message is in view however far up the user had scrolled.
+    scroll_to_bottom: bool,
+    /// Scroll offset of the message list saved when the page was last left,
+    /// if it was in the same room.
+    saved_scroll: Option<i32>,
+    /// Scroll the message list to this offset after the next render.
+    scroll_to: Option<i32>,
+    /// Apply `preferences.scroll_on_load` when the first messages arrive,
+    /// which is the history. Only once: after a reconnect the list stays
+    /// where the user left it.
+    scroll_after_load: bool,
+    /// Ids of the messages already rendered; any others are new and animate
+    /// in. Ids rather than positions, as messages numbered by the server may
+    /// be inserted before older ones.
+    rendered_ids: HashSet<String>,
     _producer: Box<dyn Bridge<EventBus>>,
-    wss: WebsocketService,
+    wss: Box<dyn Transport>,
+    /// Whether `wss` is the HTTP long-polling fallback, used after a
+    /// WebSocket couldn't be opened at all.
+    long_polling: bool,
+    connection: ConnectionState,
+    send_error: Option<WsError>,
+    /// Milliseconds between sending our recent messages and their echo.
+    round_trips: Vec<f64>,
+    /// Whether each recent frame failed to send.
+    failed_sends: Vec<bool>,
     messages: Vec<MessageData>,
+    /// Ids of long messages the user expanded.
+    expanded: HashSet<String>,
+    /// Our messages awaiting their echo, oldest first.
+    pending: Vec<PendingMessage>,
+    /// Our messages that failed to send, oldest first, until resent.
+    failed: Vec<PendingMessage>,
+    seen: SeenKeys,
+    /// Highest `seq` received, to notice messages that went missing.
+    last_seq: Option<u64>,
+    next_local_id: u64,
+    room: String,
+    rooms: Vec<String>,
+    /// Text in the composer for the current room.
+    draft: String,
+    /// Unsent composer text of the other rooms, restored when switching back.
+    drafts: HashMap<String, String>,
+    /// `@mention` being typed: byte offset of the `@` in `draft` and the
+    /// text typed after it.
+    mention: Option<(usize, String)>,
+    /// Highlighted entry of the mention suggestions.
+    mention_index: usize,
+    /// Others typing right now, by user id.
+    typing: HashMap<String, Typer>,
+    /// Users who just posted, highlighted in the roster until the timer
+    /// fires.
+    pulsing: HashMap<String, Timeout>,
+    /// Latest `users` frame not applied yet.
+    queued_roster: Option<Vec<RosterEntry>>,
+    /// Presence changes not applied yet, oldest first, by user id.
+    queued_presence: Vec<(String, Status)>,
+    /// Applies the queued roster updates when it fires.
+    roster_flush: Option<Timeout>,
+    /// Chat messages received since the last animation frame, oldest first.
+    inbound: Vec<MessageData>,
+    /// Appends `inbound` on the next animation frame, so a burst of messages
+    /// renders once rather than once each.
+    inbound_frame: Option<AnimationFrame>,
+    /// When we last sent a `typing` frame, in milliseconds since the epoch.
+    last_typing_sent: f64,
+    show_all_users: bool,
+    roster_sort: RosterSort,
+    /// Only users whose name contains this are listed in the sidebar.
+    roster_filter: String,
+    /// Id of the newest message per room that had been seen when the user
+    /// last looked away, either by switching rooms or leaving the window.
+    last_read: HashMap<String, String>,
+    only_unread: bool,
+    /// Ids of pinned messages, oldest pin first.
+    pinned: Vec<String>,
+    show_pinned: bool,
+    show_stats: bool,
+    preferences: Preferences,
+    show_settings: bool,
+    show_shortcut_help: bool,
+    pending_large_send: Option<String>,
+    show_gif_picker: bool,
+    window_focused: bool,
+    minimized: bool,
+    /// Sidebar collapsed to a thin strip.
+    focus_mode: bool,
+    /// Roster shows a status dot and name per user instead of avatars.
+    compact_roster: bool,
+    /// Sidebar drawer shown over the chat on narrow screens, where it is
+    /// otherwise hidden.
+    sidebar_open: bool,
+    unread_while_minimized: usize,
+    lightbox: Option<String>,
+    /// Locale numbers and times are formatted for.
+    locale: String,
+    /// Id of the message the report dialog is open for.
+    reporting: Option<String>,
+    /// Id of the message the forward dialog is open for.
+    forwarding: Option<String>,
+    /// Ids of messages we reported.
+    reported: HashSet<String>,
+    /// Rooms where only admins may post.
+    read_only_rooms: HashSet<String>,
+    show_poll_dialog: bool,
+    /// Option each user voted for, by poll id. Keyed by voter so a relayed
+    /// vote replaces their earlier one instead of adding to it.
+    votes: HashMap<String, HashMap<String, usize>>,
+    /// Ids of the replies to each thread root, in arrival order.
+    threads: HashMap<String, Vec<String>>,
+    /// Root id of the thread shown in the side panel.
+    thread: Option<String>,
+    /// Unsent reply in the thread panel.
+    thread_draft: String,
+    /// Message id and position of the open context menu.
+    context_menu: Option<(String, i32, i32)>,
+    /// Id of the message whose details are open.
+    details: Option<String>,
+    idle_timer: Option<Timeout>,
+    /// Why the server removed us, when it kicked us or was full. Reconnecting
+    /// is left to the user, who can rejoin once `rejoin_wait` has elapsed.
+    removed: Option<CloseReason>,
+    rejoin_wait: Option<Timeout>,
+    logout_countdown: Option<(u32, Interval)>,
+    /// Ends the flash on the message last jumped to.
+    highlight: Option<Timeout>,
+    /// Message a permalink points at, jumped to once it has been rendered.
+    jump_to: Option<String>,
+    // Timers and window listeners are owned by fields like these, so they are
+    // cancelled or removed when the component unmounts: gloo's `EventListener`,
+    // `Interval` and `Timeout` unregister themselves on drop. A remount builds
+    // a fresh set in `create` after the old `Chat` and its set were dropped,
+    // so listeners can't pile up. Never `forget()` one: every remount would
+    // leave another copy firing behind.
+    _hash_listener: EventListener,
+    _activity_listeners: [EventListener; 2],
+    _presence_listeners: [EventListener; 2],
+    _shortcut_listener: EventListener,
+    _unload_listener: EventListener,
+    _pending_sweep: Interval,
+    /// Height and top offset of the visual viewport, which shrinks when an
+    /// on-screen keyboard opens. `None` where the API is missing, leaving the
+    /// layout at the full dynamic viewport height.
+    visual_viewport: Option<(f64, f64)>,
+    _viewport_listeners: Vec<EventListener>,
+    // Created once so unchanged `MessageItem`s compare equal across renders.
+    on_quote: Callback<String>,
+    on_toggle_pin: Callback<String>,
+    on_open_image: Callback<String>,
+    on_context_menu: Callback<(String, i32, i32)>,
+    on_toggle_expanded: Callback<String>,
+    on_vote: Callback<(String, usize)>,
+    on_open_thread: Callback<String>,
 }
-impl Component for Chat {
-    type Message = Msg;
-    type Properties = ();
 
-    fn create(ctx: &Context<Self>) -> Self {
-        let (user, _) = ctx
-            .link()
-            .context::<User>(Callback::noop())
-            .expect("context to be set");
-        let wss = WebsocketService::new();
-        let username = user.username.borrow().clone();
+impl Chat {
+    /// Adds a message unless it has already been seen, so replays after a
+    /// reconnect are idempotent. Messages go at the end, or in `seq` order when
+    /// the server numbers them. Returns whether the message was added.
+    fn push_message(&mut self, mut message: MessageData) -> bool {
+        let index = match admit(&self.messages, &mut self.seen, &message) {
+            Some(index) => index,
+            None => {
+                log_duplicate_dropped(&message.from);
+                return false;
+            }
+        };
+        if let Some(seq) = message.seq {
+            if let Some(last) = self.last_seq.filter(|last| seq > last + 1) {
+                log_sequence_gap(last + 1, seq);
+            }
+            self.last_seq = self.last_seq.max(Some(seq));
+        }
+        if message.id.is_none() {
+            self.next_local_id += 1;
+            message.id = Some(format!("{}{}", LOCAL_ID_PREFIX, self.next_local_id));
+        }
+        // Servers that don't stamp messages get the time of receipt.
+        if message.timestamp.is_none() {
+            message.timestamp = Some(js_sys::Date::now() as i64);
+        }
+        if let Some(root) = &message.reply_to {
+            let replies = self.threads.entry(root.clone()).or_default();
+            replies.push(message.id().to_string());
+        }
+        let room = message.room().to_string();
+        if !self.rooms.contains(&room) {
+            self.rooms.push(room);
+        }
+        if self.minimized {
+            self.unread_while_minimized += 1;
+        }
+        self.messages.insert(index, message);
+        true
+    }
+
+    fn send(&mut self, message: &WebSocketMessage) {
+        let result = self.wss.send(message);
+        push_sample(&mut self.failed_sends, result.is_err());
+        match result {
+            Ok(()) => self.send_error = None,
+            Err(e) => {
+                log_send_error(&e);
+                self.send_error = Some(e);
+            }
+        }
+    }
+
+    /// Restarts the inactivity timer; called on every keypress, focus or send.
+    fn reset_idle_timer(&mut self, ctx: &Context<Self>) {
+        let mins = self.preferences.idle_timeout_mins;
+        self.idle_timer = (mins > 0).then(|| {
+            let idle = ctx.link().callback(|_| Msg::IdleTimeout);
+            // setTimeout fires at once for delays past `i32::MAX`.
+            let ms = mins.saturating_mul(60_000).min(i32::MAX as u32);
+            Timeout::new(ms, move || idle.emit(()))
+        });
+    }
+
+    /// Stops reconnecting after the server removed us and starts the wait
+    /// before the user may rejoin.
+    fn remove(&mut self, ctx: &Context<Self>, close: CloseReason) {
+        self.wss.close();
+        let available = ctx.link().callback(|_| Msg::RejoinAvailable);
+        self.rejoin_wait = Some(Timeout::new(REJOIN_DELAY_MS, move || available.emit(())));
+        self.removed = Some(close);
+    }
+
+    fn logout(&mut self, ctx: &Context<Self>) {
+        self.logout_countdown = None;
+        if let Some((user, _)) = ctx.link().context::<User>(Callback::noop()) {
+            user.username.borrow_mut().clear();
+        }
+        // Navigating away unmounts the component, dropping `wss` and with it
+        // the socket.
+        if let Some(history) = ctx.link().history() {
+            history.push(Route::Login);
+        }
+    }
+
+    /// Registers us, shares our profile and joins the current room whenever
+    /// a connection opens. Called again whenever any of those change.
+    fn update_handshake(&mut self) {
+        let frames = [
+            WebSocketMessage {
+                message_type: MsgTypes::Register,
+                data: Some(self.username.clone()),
+                data_array: None,
+                room: None,
+                version: PROTOCOL_VERSION,
+                ephemeral: false,
+                reply_to: None,
+                forwarded_from: None,
+            },
+            self.profile_message(),
+            self.join_message(),
+        ];
+        if let Err(e) = self.wss.set_handshake(&frames) {
+            log_send_error(&e);
+            self.send_error = Some(e);
+        }
+    }
+
+    /// Shares our avatar set so other clients draw us the same way. The
+    /// payload is `{"avatar_set": 3}`; servers include it as `avatar_set` in
+    /// roster entries.
+    fn profile_message(&self) -> WebSocketMessage {
+        let profile = serde_json::json!({ "avatar_set": self.preferences.avatar_set });
+        WebSocketMessage {
+            message_type: MsgTypes::Profile,
+            data: Some(profile.to_string()),
+            data_array: None,
+            room: None,
+            version: PROTOCOL_VERSION,
+            ephemeral: false,
+            reply_to: None,
+            forwarded_from: None,
+        }
+    }
 
-        let message = WebSocketMessage {
-            message_type: MsgTypes::Register,
-            data: Some(username.to_string()),
+    fn join_message(&self) -> WebSocketMessage {
+        WebSocketMessage {
+            message_type: MsgTypes::Join,
+            data: Some(self.room.clone()),
             data_array: None,
+            room: None,
+            version: PROTOCOL_VERSION,
+            ephemeral: false,
+            reply_to: None,
+            forwarded_from: None,
+        }
+    }
+
+    /// Avatar set for a user the server didn't report one for: our own
+    /// choice for us, the default for everyone else.
+    fn avatar_set_for(&self, user_id: &str) -> u8 {
+        if user_id == self.user_id {
+            self.preferences.avatar_set
+        } else {
+            DEFAULT_AVATAR_SET
+        }
+    }
+
+    fn join_room(&mut self, room: String) {
+        if !self.rooms.contains(&room) {
+            self.rooms.push(room.clone());
+        }
+        if room != self.room {
+            self.mark_room_read();
+            let draft = std::mem::take(&mut self.draft);
+            self.drafts
+                .insert(std::mem::replace(&mut self.room, room), draft);
+            let restored = self.drafts.remove(&self.room).unwrap_or_default();
+            self.set_input(restored);
+            // The throttle is for the room we left; tell the new one right
+            // away when we start typing there.
+            self.last_typing_sent = 0.0;
+            self.thread = None;
+        }
+        self.send(&self.join_message());
+        self.update_handshake();
+    }
+
+    /// Recently sent messages still awaiting their echo.
+    fn backlog(&self) -> usize {
+        let now = js_sys::Date::now();
+        self.pending
+            .iter()
+            .filter(|p| now - p.sent_at < BACKLOG_WINDOW_MS)
+            .count()
+    }
+
+    /// Whether too many messages await their echo to send more.
+    fn backed_up(&self) -> bool {
+        self.backlog() >= MAX_BACKLOG
+    }
+
+    /// Whether we may post in the current room.
+    fn can_post(&self) -> bool {
+        self.can_post_in(&self.room)
+    }
+
+    /// Whether we may post in `room`. Our role comes from the roster, so
+    /// until it arrives we count as a member.
+    fn can_post_in(&self, room: &str) -> bool {
+        !self.read_only_rooms.contains(room)
+            || self
+                .users
+                .iter()
+                .any(|u| u.id == self.user_id && u.role == Role::Admin)
+    }
+
+    /// Sends a message from the composer and clears it, unless the server is
+    /// backed up, in which case the text stays put.
+    fn send_chat_message(&mut self, text: String, ephemeral: bool, reply_to: Option<String>) {
+        let message = PendingMessage {
+            text,
+            room: self.room.clone(),
+            ephemeral,
+            reply_to: reply_to.clone(),
+            forwarded_from: None,
+            sent_at: js_sys::Date::now(),
         };
+        if !self.post(message) {
+            return;
+        }
+        if reply_to.is_some() {
+            self.thread_draft.clear();
+        } else {
+            self.set_input(String::new());
+            self.scroll_to_bottom = true;
+        }
+    }
 
-        if let Ok(_) = wss
-            .tx
-            .clone()
-            .try_send(serde_json::to_string(&message).unwrap())
-        {
-            log::debug!("message sent successfully");
+    /// Moves messages whose echo never came to `failed`, where they can be
+    /// retried. Returns whether any did.
+    fn expire_pending(&mut self) -> bool {
+        let now = js_sys::Date::now();
+        let (expired, pending): (Vec<_>, Vec<_>) = std::mem::take(&mut self.pending)
+            .into_iter()
+            .partition(|p| now - p.sent_at >= BACKLOG_WINDOW_MS);
+        self.pending = pending;
+        if expired.is_empty() {
+            return false;
         }
+        log_sends_expired(expired.len());
+        self.failed.extend(expired);
+        true
+    }
 
-        Self {
-            users: vec![],
-            messages: vec![],
-            chat_input: NodeRef::default(),
-            wss,
-            _producer: EventBus::bridge(ctx.link().callback(Msg::HandleMsg)),
+    /// Sends a chat message to its room and tracks it until the echo arrives,
+    /// or as failed. Returns false, sending nothing, while the server is backed
+    /// up or if we may not post in the room.
+    fn post(&mut self, message: PendingMessage) -> bool {
+        if self.backed_up() || !self.can_post_in(&message.room) {
+            return false;
         }
+        let duplicate = self.pending.iter().any(|p| {
+            p.text == message.text
+                && p.room == message.room
+                && message.sent_at - p.sent_at < DUPLICATE_SEND_WINDOW_MS
+        });
+        if duplicate {
+            log::debug!(target: "chat", "ignored duplicate send while pending");
+            return true;
+        }
+        self.send(&message.frame());
+        if self.send_error.is_none() {
+            self.pending.push(message);
+            // Others stop showing us as typing once the message arrives,
+            // so announce the next one straight away.
+            self.last_typing_sent = 0.0;
+        } else {
+            self.failed.push(message);
+        }
+        true
     }
 
-    fn update(&mut self, _ctx: &Context<Self>, msg: Self::Message) -> bool {
-        match msg {
-            Msg::HandleMsg(s) => {
-                let msg: WebSocketMessage = serde_json::from_str(&s).unwrap();
-                match msg.message_type {
-                    MsgTypes::Users => {
-                        let users_from_message = msg.data_array.unwrap_or_default();
-                        self.users = users_from_message
-                            .iter()
-                            .map(|u| UserProfile {
-                                name: u.into(),
-                                avatar: generate_avatar_for_user(u),
-                            })
-                            .collect();
-                        return true;
-                    }
-                    MsgTypes::Message => {
-                        let message_data: MessageData =
-                            serde_json::from_str(&msg.data.unwrap()).unwrap();
-                        self.messages.push(message_data);
-                        return true;
-                    }
-                    _ => {
-                        return false;
+    /// Sends the messages that failed earlier, oldest first. Stops at the
+    /// first one that fails again so the rest keep their order.
+    fn resend_failed(&mut self) {
+        let mut failed = std::mem::take(&mut self.failed).into_iter();
+        for mut message in failed.by_ref() {
+            if self.backed_up() {
+                self.failed.push(message);
+                break;
+            }
+            self.send(&message.frame());
+            if self.send_error.is_some() {
+                self.failed.push(message);
+                break;
+            }
+            message.sent_at = js_sys::Date::now();
+            self.pending.push(message);
+        }
+        self.failed.extend(failed);
+    }
+
+    /// Runs a slash command. Apart from /whisper, which sends its message,
+    /// commands are answered with a bot message in the current room and
+    /// nothing is sent to the server. Returns false for unknown commands,
+    /// which are left to be sent as ordinary text, e.g. "/shrug" or a path.
+    fn run_command(&mut self, command: &str) -> bool {
+        let mut words = command.split_whitespace();
+        let name = words.next().unwrap_or_default();
+        let reply = match name {
+            "help" => COMMANDS
+                .iter()
+                .map(|(name, description)| format!("/{} – {}", name, description))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            "template" => {
+                let wanted = words.next().unwrap_or_default();
+                let template = self.preferences.templates.iter().find(|t| t.name == wanted);
+                if let Some(template) = template {
+                    let text = template.text.clone();
+                    let cursor = text.encode_utf16().count() as u32;
+                    self.set_input(text);
+                    if let Some(input) = self.chat_input.cast::<HtmlTextAreaElement>() {
+                        let _ = input.focus();
+                        let _ = input.set_selection_range(cursor, cursor);
                     }
+                    return true;
+                }
+                let names: Vec<&str> = self
+                    .preferences
+                    .templates
+                    .iter()
+                    .map(|t| t.name.as_str())
+                    .collect();
+                match (wanted, names.is_empty()) {
+                    (_, true) => "You have no templates yet. Add some in Settings.".to_string(),
+                    ("", false) => format!(
+                        "Usage: /template <name>. Your templates: {}",
+                        names.join(", ")
+                    ),
+                    (_, false) => format!(
+                        "No template named \"{}\". Your templates: {}",
+                        wanted,
+                        names.join(", ")
+                    ),
                 }
             }
-            Msg::SubmitMessage => {
-                let input = self.chat_input.cast::<HtmlInputElement>();
-                if let Some(input) = input {
-                    let message = WebSocketMessage {
-                        message_type: MsgTypes::Message,
-                        data: Some(input.value()),
-                        data_array: None,
-                    };
-                    if let Err(e) = self
-                        .wss
-                        .tx
-                        .clone()
-                        .try_send(serde_json::to_string(&message).unwrap())
-                    {
-                        log::debug!("error sending to channel: {:?}", e);
-                    }
-                    input.set_value("");
-                };
-                false
+            "whisper" => {
+                let text = command.trim_start()[name.len()..].trim();
+                if !text.is_empty() {
+                    self.send_chat_message(text.to_string(), true, None);
+                    return true;
+                }
+                "Usage: /whisper <message>".to_string()
             }
+            _ => return false,
+        };
+        self.push_message(MessageData {
+            from: SYSTEM_SENDER.to_string(),
+            message: reply,
+            id: None,
+            timestamp: None,
+            room: Some(self.room.clone()),
+            user_id: None,
+            metadata: None,
+            bot: true,
+            ephemeral: false,
+            seq: None,
+            reply_to: None,
+            forwarded_from: None,
+            edited_at: None,
+            edit_history: Vec::new(),
+        });
+        self.set_input(String::new());
+        self.scroll_to_bottom = true;
+        true
+    }
+
+    /// Records `user_id`'s vote on a poll, `None` taking it back. Votes for
+    /// options the poll doesn't have are ignored. Returns whether anything
+    /// changed.
+    fn set_vote(&mut self, poll_id: String, user_id: String, option: Option<usize>) -> bool {
+        if option.is_some_and(|option| option >= self.poll_options(&poll_id)) {
+            log::debug!(target: "chat", "ignored vote for a missing poll option");
+            return false;
+        }
+        let votes = self.votes.entry(poll_id).or_default();
+        match option {
+            Some(option) => votes.insert(user_id, option) != Some(option),
+            None => votes.remove(&user_id).is_some(),
         }
     }
 
-    fn view(&self, ctx: &Context<Self>) -> Html {
-        let submit = ctx.link().callback(|_| Msg::SubmitMessage);
+    /// Number of options on a poll, or the most any poll may have while its
+    /// message hasn't arrived.
+    fn poll_options(&self, poll_id: &str) -> usize {
+        self.messages
+            .iter()
+            .find(|m| m.id() == poll_id)
+            .and_then(|m| {
+                m.metadata
+                    .as_ref()?
+                    .get("options")?
+                    .as_array()
+                    .map(Vec::len)
+            })
+            .map_or(MAX_OPTIONS, |count| count.min(MAX_OPTIONS))
+    }
+
+    fn quality(&self) -> ConnectionQuality {
+        ConnectionQuality::assess(&self.round_trips, &self.failed_sends)
+    }
+
+    fn mention_suggestions(&self) -> Vec<String> {
+        match &self.mention {
+            Some((_, query)) => match_users(query, &self.users),
+            None => vec![],
+        }
+    }
+
+    /// Records activity from a user for sorting the roster.
+    fn touch_user(&mut self, user_id: &str) {
+        if let Some(user) = self.users.iter_mut().find(|u| u.id == user_id) {
+            user.last_active = js_sys::Date::now();
+        }
+    }
+
+    /// Roster in the chosen order. The sort is stable, so ties keep the
+    /// server's order.
+    fn sorted_users(&self) -> Vec<&UserProfile> {
+        let mut users: Vec<&UserProfile> = self
+            .users
+            .iter()
+            .filter(|u| name_contains(&u.name, &self.roster_filter))
+            .collect();
+        match self.roster_sort {
+            RosterSort::Alphabetical => {
+                users.sort_by_key(|u| u.name.to_lowercase());
+            }
+            RosterSort::RecentActivity => {
+                users.sort_by(|a, b| b.last_active.total_cmp(&a.last_active));
+            }
+        }
+        users
+    }
+
+    /// Marks every message currently in the room as read.
+    fn mark_room_read(&mut self) {
+        if let Some(last) = self.messages.iter().rev().find(|m| m.room() == self.room) {
+            self.last_read
+                .insert(self.room.clone(), last.id().to_string());
+        }
+    }
+
+    /// Messages in `room` that arrived since it was last left.
+    fn unread_in(&self, room: &str) -> usize {
+        unread(
+            &self.messages,
+            room,
+            self.last_read.get(room).map(String::as_str),
+        )
+        .count()
+    }
+
+    fn can_pin(&self) -> bool {
+        PIN_ALLOWED_USERS.is_none_or(|users| users.split(',').any(|u| u.trim() == self.user_id))
+    }
+
+    fn set_pinned(&mut self, id: String, pinned: bool) -> bool {
+        let index = self.pinned.iter().position(|p| *p == id);
+        match (index, pinned) {
+            (None, true) => self.pinned.push(id),
+            (Some(index), false) => {
+                self.pinned.remove(index);
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// Replaces the composer's text, keeping `draft` in sync. Any `@`
+    /// suggestions belonged to the old text and are dismissed.
+    fn set_input(&mut self, value: String) {
+        if let Some(input) = self.chat_input.cast::<HtmlTextAreaElement>() {
+            input.set_value(&value);
+        }
+        self.draft = value;
+        self.mention = None;
+        self.mention_index = 0;
+    }
+
+    /// Announces our status and reflects it on our own roster entry right
+    /// away instead of waiting for the server to relay it back.
+    fn set_presence(&mut self, status: Status) {
+        self.set_user_status(&self.user_id.clone(), status);
+        self.send(&WebSocketMessage {
+            message_type: MsgTypes::Presence,
+            data: Some(status.as_str().to_string()),
+            data_array: None,
+            room: None,
+            version: PROTOCOL_VERSION,
+            ephemeral: false,
+            reply_to: None,
+            forwarded_from: None,
+        });
+    }
+
+    /// Takes in the buffered chat messages. Returns whether any was new.
+    fn flush_inbound(&mut self, ctx: &Context<Self>) -> bool {
+        let mut any_appended = false;
+        let mut notify = false;
+        for message_data in std::mem::take(&mut self.inbound) {
+            let from_other = message_data.user_id() != self.user_id;
+            if !from_other {
+                let acked = self
+                    .pending
+                    .iter()
+                    .position(|p| p.text == message_data.message && p.room == message_data.room());
+                if let Some(index) = acked {
+                    let sent = self.pending.remove(index);
+                    push_sample(&mut self.round_trips, js_sys::Date::now() - sent.sent_at);
+                }
+            }
+            // A message ends its sender's typing, without waiting for the
+            // indicator to expire.
+            self.typing.remove(message_data.user_id());
+            self.touch_user(message_data.user_id());
+            let sender = message_data.user_id().to_string();
+            let appended = self.push_message(message_data);
+            if appended {
+                let ended = ctx.link().callback(Msg::PulseEnded);
+                let id = sender.clone();
+                self.pulsing.insert(
+                    sender,
+                    Timeout::new(POST_PULSE_MS, move || ended.emit(id.clone())),
+                );
+            }
+            any_appended |= appended;
+            notify |= appended && from_other;
+        }
+        if any_appended && std::mem::take(&mut self.scroll_after_load) {
+            match self.preferences.scroll_on_load {
+                ScrollOnLoad::Latest => self.scroll_to_bottom = true,
+                ScrollOnLoad::Restore => self.scroll_to = self.saved_scroll.take(),
+            }
+        }
+        // Once per burst, however many messages it held.
+        if notify
+            && !self.window_focused
+            && self.preferences.notification_sound
+            && !self.preferences.notifications_muted(TimeOfDay::now())
+        {
+            play_notification();
+        }
+        any_appended
+    }
+
+    /// Starts the timer applying queued roster updates, unless it's running.
+    fn schedule_roster_flush(&mut self, ctx: &Context<Self>) {
+        if self.roster_flush.is_none() {
+            let flush = ctx.link().callback(|_| Msg::FlushRoster);
+            self.roster_flush = Some(Timeout::new(ROSTER_BATCH_MS, move || flush.emit(())));
+        }
+    }
+
+    /// Replaces the roster, keeping what we know about users still in it.
+    fn apply_roster(&mut self, roster: Vec<RosterEntry>) {
+        self.users = roster
+            .into_iter()
+            .map(|u| {
+                let id = u.id.unwrap_or_else(|| u.name.clone());
+                let set = u.avatar_set.unwrap_or_else(|| self.avatar_set_for(&id));
+                let last_active = self
+                    .users
+                    .iter()
+                    .find(|known| known.id == id)
+                    .map_or(0.0, |known| known.last_active);
+                UserProfile {
+                    last_active,
+                    avatar: avatar_url(&id, set),
+                    id,
+                    name: u.name,
+                    room: u.room,
+                    status: u.status,
+                    role: u.role,
+                }
+            })
+            .collect();
+        self.initial_loaded = true;
+    }
+
+    fn set_user_status(&mut self, user_id: &str, status: Status) -> bool {
+        match self.users.iter_mut().find(|u| u.id == user_id) {
+            Some(user) if user.status != status => {
+                user.status = status;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    #[allow(clippy::unnecessary_operation)]
+    fn view_logout_warning(&self, ctx: &Context<Self>) -> Html {
+        match &self.logout_countdown {
+            Some((secs, _)) => html! {
+                <Modal title="Are you still there?">
+                    <p class="mb-4">{format!("You'll be logged out in {}s.", secs)}</p>
+                    <div class="flex justify-end">
+                        <button onclick={ctx.link().callback(|_| Msg::StayLoggedIn)} class="px-4 py-2 rounded-lg bg-blue-600 text-white hover:bg-blue-700">{"Stay"}</button>
+                    </div>
+                </Modal>
+            },
+            None => html! {},
+        }
+    }
 
+    /// Shown instead of the chat when the server couldn't be reached at all.
+    fn view_unreachable(&self, ctx: &Context<Self>) -> Html {
         html! {
-        <div class="flex min-h-screen w-screen">
-            <aside class="w-64 bg-gray-100 p-4">
-                <h2 class="text-xl font-bold mb-4">{"Users"}</h2>
-                {for self.users.iter().map(|user| {
-                    html!{
-                        <div class="flex items-center bg-white rounded-lg p-2 mb-2 shadow hover:bg-gray-50">
-                            <img class="w-12 h-12 rounded-full" src={user.avatar.clone()} alt={format!("{}'s avatar", user.name)} />
-                            <div class="ml-4">
-                                <p class="text-sm font-medium">{&user.name}</p>
-                                <p class="text-xs text-gray-400">{"Active now"}</p>
-                            </div>
-                        </div>
-                    }
-                })}
-            </aside>
-            <main class="flex-grow flex flex-col bg-gray-50">
-                <header class="bg-white shadow p-4">
-                    <h1 class="text-xl font-bold">{"💬 Chat"}</h1>
-                </header>
-                <div class="flex-grow overflow-auto p-4">
-                    {for self.messages.iter().map(|message| {
-                        let user = self.users.iter().find(|u| u.name == message.from).unwrap();
-                        html!{
-                            <div class="flex items-end mb-4">
-                                <img class="w-8 h-8 rounded-full mr-3" src={user.avatar.clone()} alt={format!("{}'s avatar", user.name)} />
-                                <div class="flex flex-col bg-white rounded-lg p-3 shadow">
-                                    <span class="text-sm font-medium">{&message.from}</span>
-                                    <span class="text-gray-600 text-xs">
-                                        {if message.message.ends_with(".gif") {
-                                            html! { <img src={message.message.clone()} alt="gif image" /> }
-                                        } else {
-                                            html! { <p>{&message.message}</p> }
-                                        }}
-                                    </span>
-                                </div>
-                            </div>
-                        }
-                    })}
+            <div role="alert" class="flex flex-col items-center justify-center w-screen min-h-screen bg-gray-50 text-center p-4">
+                <p class="text-5xl mb-4">{"🔌"}</p>
+                <h1 class="text-xl font-bold mb-2">{"Can't reach the chat server"}</h1>
+                <p class="text-sm text-gray-500 mb-6">{"Make sure the server is running, then try again."}</p>
+                <button onclick={ctx.link().callback(|_| Msg::Reconnect)} class="px-4 py-2 rounded-lg bg-blue-600 text-white hover:bg-blue-700">{"Retry"}</button>
+            </div>
+        }
+    }
+
+    /// Shown instead of the chat after the server removed us or turned us
+    /// away, with a rejoin button that unlocks after a delay.
+    fn view_removed(&self, ctx: &Context<Self>, close: &CloseReason) -> Html {
+        let title = if close.code == CloseReason::SERVER_FULL {
+            "The chat is full".to_string()
+        } else if close.reason.is_empty() {
+            "You were removed from the chat".to_string()
+        } else {
+            format!("You were removed from the chat: {}", close.reason)
+        };
+        let waiting = self.rejoin_wait.is_some();
+        html! {
+            <div role="alert" class="flex flex-col items-center justify-center w-screen min-h-screen bg-gray-50 text-center p-4">
+                <p class="text-5xl mb-4">{"🚪"}</p>
+                <h1 class="text-xl font-bold mb-2">{title}</h1>
+                <p class="text-sm text-gray-500 mb-6">
+                    {if waiting {
+                        format!("You can try to rejoin in {} seconds.", REJOIN_DELAY_MS / 1_000)
+                    } else {
+                        "You can try to rejoin now.".to_string()
+                    }}
+                </p>
+                <div class="flex">
+                    <button onclick={ctx.link().callback(|_| Msg::Reconnect)} disabled={waiting} class="px-4 py-2 rounded-lg bg-blue-600 text-white hover:bg-blue-700 disabled:opacity-50 disabled:cursor-not-allowed">{"Rejoin"}</button>
+                    <button onclick={ctx.link().callback(|_| Msg::Logout)} class="ml-4 px-4 py-2 rounded-lg text-blue-600 hover:underline">{"Back to login"}</button>
                 </div>
-                <footer class="flex items-center p-4 bg-white shadow">
-                    <input ref={self.chat_input.clone()} type="text" placeholder="Type a message..." class="flex-grow rounded-full border-2 border-gray-300 p-2 mr-2 focus:border-blue-500 outline-none" />
-                    <button onclick={submit} class="flex justify-center items-center w-12 h-12 text-white bg-blue-600 rounded-full hover:bg-blue-700 focus:outline-none">
-                        <svg class="w-6 h-6" fill="none" stroke="currentColor" viewBox="0 0 24 24" xmlns="http://www.w3.org/2000/svg"><path stroke-linecap="round" stroke-linejoin="round" stroke-width="2" d="M5 13l4 4L19 7"></path></svg>
-                    </button>
-                </footer>
-            </main>
-        </div>
+            </div>
+        }
     }
+
+    /// Floating bubble shown instead of the full layout while minimized.
+    fn view_minimized(&self, ctx: &Context<Self>) -> Html {
+        html! {
+            <>
+            <button onclick={ctx.link().callback(|_| Msg::ToggleMinimize)} title="Open chat" class="fixed bottom-4 right-4 z-40 flex items-center bg-white rounded-full shadow-lg p-2 hover:bg-gray-50">
+                <div class="flex -space-x-3">
+                    {for self.users.iter().take(3).map(|user| html! {
+                        <img key={user.id.clone()} class="w-10 h-10 rounded-full border-2 border-white bg-gray-100" src={user.avatar.clone()} alt={format!("{}'s avatar", user.name)} />
+                    })}
+                </div>
+                <span class="mx-2 text-xl">{"💬"}</span>
+                if self.unread_while_minimized > 0 {
+                    <span class="absolute -top-1 -right-1 min-w-6 h-6 px-1 flex items-center justify-center rounded-full bg-red-600 text-white text-xs font-bold">
+                        {format_count(self.unread_while_minimized, &self.locale)}
+                    </span>
+                }
+            </button>
+            {self.view_logout_warning(ctx)}
+            </>
+        }
+    }
+
+    fn view_pinned(&self, ctx: &Context<Self>) -> Html {
+        let pinned: Vec<&MessageData> = self
+            .pinned
+            .iter()
+            .filter_map(|id| self.messages.iter().find(|m| m.id() == id))
+            .filter(|m| m.room() == self.room)
+            .collect();
+        if pinned.is_empty() {
+            return html! {};
+        }
+        html! {
+            <section class="bg-yellow-50 border-b border-yellow-200 px-4 py-2">
+                <button onclick={ctx.link().callback(|_| Msg::TogglePinnedPanel)} class="text-sm font-bold text-yellow-800">
+                    {format!("{} 📌 Pinned ({})", if self.show_pinned { "▾" } else { "▸" }, format_count(pinned.len(), &self.locale))}
+                </button>
+                if self.show_pinned {
+                    {for pinned.into_iter().map(|message| {
+                        let id = message.id().to_string();
+                        let jump = ctx.link().callback(move |_| Msg::JumpToMessage(id.clone()));
+                        html! {
+                            <p key={message.id().to_string()} onclick={jump} class="text-sm truncate cursor-pointer hover:underline">
+                                <span class="font-medium">{&message.from}{": "}</span>{&message.message}
+                            </p>
+                        }
+                    })}
+                }
+            </section>
+        }
+    }
+
+    fn view_backlog(&self) -> Html {
+        let waiting = self.backlog();
+        if waiting < BACKLOG_NOTICE {
+            return html! {};
+        }
+        let text = if waiting >= MAX_BACKLOG {
+            format!(
+                "{} messages are waiting for the server. Sending is paused until it catches up.",
+                waiting
+            )
+        } else {
+            format!("{} messages are waiting for the server.", waiting)
+        };
+        html! {
+            <p role="status" class="px-4 text-xs text-yellow-700">{text}</p>
+        }
+    }
+
+    fn view_typing(&self) -> Html {
+        let mut names: Vec<String> = self
+            .typing
+            .values()
+            .filter(|t| t.room == self.room)
+            .map(|t| t.name.clone())
+            .collect();
+        names.sort();
+        html! {
+            <p class="h-5 px-4 text-xs text-gray-400 italic">{typing_summary(&names)}</p>
+        }
+    }
+
+    #[allow(clippy::unnecessary_operation)]
+    fn view_context_menu(&self, ctx: &Context<Self>) -> Html {
+        let (id, x, y) = match &self.context_menu {
+            Some(menu) => menu.clone(),
+            None => return html! {},
+        };
+        let message = match self.messages.iter().find(|m| m.id() == id) {
+            Some(message) => message,
+            None => return html! {},
+        };
+        let mut items = vec![MenuItem {
+            label: "Copy",
+            on_select: ctx.link().callback({
+                let id = id.clone();
+                move |_| Msg::CopyMessage(id.clone())
+            }),
+        }];
+        // Client-generated ids differ between clients, so don't link to them.
+        if !message.has_local_id() {
+            items.push(MenuItem {
+                label: "Copy link",
+                on_select: ctx.link().callback({
+                    let id = id.clone();
+                    move |_| Msg::CopyMessageLink(id.clone())
+                }),
+            });
+        }
+        items.push(MenuItem {
+            label: "Reply",
+            on_select: ctx.link().callback({
+                let text = message.message.clone();
+                move |_| Msg::Quote(text.clone())
+            }),
+        });
+        // Threads are keyed by server id; a reply continues its root's thread.
+        if !message.has_local_id() {
+            let root = message.reply_to.clone().unwrap_or_else(|| id.clone());
+            items.push(MenuItem {
+                label: "Reply in thread",
+                on_select: ctx.link().callback(move |_| Msg::OpenThread(root.clone())),
+            });
+        }
+        if self
+            .rooms
+            .iter()
+            .any(|room| *room != self.room && self.can_post_in(room))
+        {
+            items.push(MenuItem {
+                label: "Forward",
+                on_select: ctx.link().callback({
+                    let id = id.clone();
+                    move |_| Msg::OpenForward(id.clone())
+                }),
+            });
+        }
+        if !message.has_local_id()
+            && message.user_id() != self.user_id
+            && !self.reported.contains(&id)
+        {
+            items.push(MenuItem {
+                label: "Report",
+                on_select: ctx.link().callback({
+                    let id = id.clone();
+                    move |_| Msg::OpenReport(id.clone())
+                }),
+            });
+        }
+        if self.can_pin() && !message.has_local_id() {
+            let pinned = self.pinned.contains(&id);
+            items.push(MenuItem {
+                label: if pinned { "Unpin" } else { "Pin" },
+                on_select: ctx.link().callback({
+                    let id = id.clone();
+                    move |_| Msg::TogglePin(id.clone())
+                }),
+            });
+        }
+        items.push(MenuItem {
+            label: "Details",
+            on_select: ctx.link().callback(move |_| Msg::ShowDetails(id.clone())),
+        });
+        html! {
+            <ContextMenu {x} {y} {items} on_close={ctx.link().callback(|_| Msg::CloseContextMenu)} />
+        }
+    }
+
+    /// Side panel with the open thread: its root message, the replies to it,
+    /// and an input for replying.
+    fn view_thread(&self, ctx: &Context<Self>) -> Html {
+        let root = match &self.thread {
+            Some(root) => root,
+            None => return html! {},
+        };
+        let replies: Vec<&MessageData> = self
+            .threads
+            .get(root)
+            .into_iter()
+            .flatten()
+            .filter_map(|id| self.messages.iter().find(|m| m.id() == id))
+            .collect();
+        let view_message = |message: &MessageData| {
+            html! {
+                <div key={message.id().to_string()} class="mb-3">
+                    <div class="flex items-baseline justify-between text-sm">
+                        <span class="font-medium">{&message.from}</span>
+                        <span class="ml-2 text-xs text-gray-400">
+                            {message.timestamp.map(|t| format_timestamp(t, self.preferences.time_format, &self.locale)).unwrap_or_default()}
+                        </span>
+                    </div>
+                    <div class="text-gray-600 text-xs select-text break-words">{render_text(&message.message)}</div>
+                </div>
+            }
+        };
+        let send_key = self.preferences.send_key;
+        let onkeydown = ctx.link().batch_callback(move |e: KeyboardEvent| {
+            let send = e.key() == "Enter"
+                && !e.is_composing()
+                && send_key.sends(e.ctrl_key() || e.meta_key(), e.shift_key());
+            send.then(|| {
+                e.prevent_default();
+                Msg::SubmitThreadReply
+            })
+        });
+        let oninput = ctx.link().callback(|e: InputEvent| {
+            let input: HtmlTextAreaElement = e.target_unchecked_into();
+            Msg::ThreadInputChanged(input.value())
+        });
+        let can_post = self.can_post();
+        html! {
+            <aside aria-label="Thread" class="fixed inset-y-0 right-0 z-40 w-80 max-w-full flex flex-col bg-white border-l border-gray-200 shadow-lg lg:static lg:shadow-none">
+                <header class="flex items-center justify-between p-4 border-b border-gray-100">
+                    <h2 class="text-lg font-bold">{"Thread"}</h2>
+                    <button onclick={ctx.link().callback(|_| Msg::CloseThread)} title="Close thread" class="text-xl text-gray-500 hover:text-gray-800">{"✕"}</button>
+                </header>
+                <div class="flex-grow overflow-auto p-4">
+                    {match self.messages.iter().find(|m| m.id() == root) {
+                        Some(message) => view_message(message),
+                        None => html! { <p class="mb-3 text-xs italic text-gray-400">{"The original message isn't loaded."}</p> },
+                    }}
+                    <p class="mb-3 pb-1 border-b border-gray-100 text-xs text-gray-400">
+                        {if replies.len() == 1 { "1 reply".to_string() } else { format!("{} replies", format_count(replies.len(), &self.locale)) }}
+                    </p>
+                    {for replies.iter().copied().map(view_message)}
+                    {for self.pending.iter().filter(|p| p.reply_to.as_ref() == Some(root)).map(|p| html! {
+                        <div class="mb-3 opacity-60 text-gray-600 text-xs whitespace-pre-wrap">{&p.text}</div>
+                    })}
+                </div>
+                <footer class="flex items-center p-4 border-t border-gray-100">
+                    <textarea value={self.thread_draft.clone()} {oninput} {onkeydown} disabled={!can_post} rows="1" placeholder={if can_post { "Reply…" } else { "Only admins can post here" }} aria-label="Reply in thread" class="flex-grow resize-none rounded-2xl border-2 border-gray-300 p-2 mr-2 focus:border-blue-500 outline-none disabled:bg-gray-100 disabled:cursor-not-allowed"></textarea>
+                    <button onclick={ctx.link().callback(|_| Msg::SubmitThreadReply)} disabled={self.thread_draft.trim().is_empty() || self.backed_up() || !can_post} class="px-3 py-2 rounded-lg bg-blue-600 text-white text-sm hover:bg-blue-700 disabled:opacity-50">{"Reply"}</button>
+                </footer>
+            </aside>
+        }
+    }
+
+    /// Lists the other rooms we may post in to forward a message to.
+    #[allow(clippy::unnecessary_operation)]
+    fn view_forward(&self, ctx: &Context<Self>) -> Html {
+        if self.forwarding.is_none() {
+            return html! {};
+        }
+        let rooms = self
+            .rooms
+            .iter()
+            .filter(|room| **room != self.room && self.can_post_in(room));
+        html! {
+            <Modal title="Forward to" on_close={ctx.link().callback(|_| Msg::CloseForward)}>
+                <ul class="mb-4">
+                    {for rooms.map(|room| {
+                        let forward = {
+                            let room = room.clone();
+                            ctx.link().callback(move |_| Msg::Forward(room.clone()))
+                        };
+                        html! {
+                            <li key={room.clone()}>
+                                <button onclick={forward} class="w-full text-left rounded-lg px-3 py-2 hover:bg-gray-100">{format!("# {}", room)}</button>
+                            </li>
+                        }
+                    })}
+                </ul>
+                <div class="flex justify-end">
+                    <button onclick={ctx.link().callback(|_| Msg::CloseForward)} class="px-4 py-2 rounded-lg text-gray-600 hover:bg-gray-100">{"Cancel"}</button>
+                </div>
+            </Modal>
+        }
+    }
+
+    /// Read-only dump of what we know about a message, for tracking down
+    /// ordering and duplicate issues.
+    #[allow(clippy::unnecessary_operation)]
+    fn view_details(&self, ctx: &Context<Self>) -> Html {
+        let message = match &self.details {
+            Some(id) => match self.messages.iter().find(|m| m.id() == id) {
+                Some(message) => message,
+                None => return html! {},
+            },
+            None => return html! {},
+        };
+        let yes_no = |value: bool| if value { "yes" } else { "no" }.to_string();
+        let id = if message.has_local_id() {
+            format!("{} (assigned here; the server sent none)", message.id())
+        } else {
+            message.id().to_string()
+        };
+        let sent = message.timestamp.map_or_else(String::new, |t| {
+            let date = js_sys::Date::new(&(t as f64).into());
+            format!(
+                "{} ({})",
+                format_timestamp(t, self.preferences.time_format, &self.locale),
+                String::from(date.to_iso_string())
+            )
+        });
+        let rows = [
+            ("Id", id),
+            ("Sender", message.from.clone()),
+            ("User id", message.user_id().to_string()),
+            ("Room", message.room().to_string()),
+            ("Sent", sent),
+            ("Status", "Delivered".to_string()),
+            ("Bot", yes_no(message.bot)),
+            ("Ephemeral", yes_no(message.ephemeral)),
+            ("Reply to", message.reply_to.clone().unwrap_or_default()),
+            (
+                "Pinned",
+                yes_no(self.pinned.iter().any(|p| p == message.id())),
+            ),
+            ("Reported", yes_no(self.reported.contains(message.id()))),
+        ];
+        html! {
+            <Modal title="Message details" on_close={ctx.link().callback(|_| Msg::CloseDetails)}>
+                <dl class="grid grid-cols-3 gap-x-4 gap-y-1 mb-4 text-sm">
+                    {for rows.into_iter().map(|(name, value)| html! {
+                        <>
+                            <dt class="text-gray-500">{name}</dt>
+                            <dd class="col-span-2 break-all select-text">{value}</dd>
+                        </>
+                    })}
+                </dl>
+                if let Some(metadata) = &message.metadata {
+                    <p class="mb-1 text-sm text-gray-500">{"Metadata"}</p>
+                    <pre class="max-h-48 overflow-auto mb-4 rounded bg-gray-100 p-2 text-xs select-text">{serde_json::to_string_pretty(metadata).unwrap_or_default()}</pre>
+                }
+                <div class="flex justify-end">
+                    <button onclick={ctx.link().callback(|_| Msg::CloseDetails)} class="px-4 py-2 rounded-lg bg-blue-600 text-white hover:bg-blue-700">{"Close"}</button>
+                </div>
+            </Modal>
+        }
+    }
+
+    fn view_stats(&self, ctx: &Context<Self>) -> Html {
+        let rate = messages_per_minute(&self.messages, js_sys::Date::now() as i64);
+        html! {
+            <section class="mt-6 text-sm text-gray-600">
+                <button onclick={ctx.link().callback(|_| Msg::ToggleStats)} class="w-full text-left font-bold mb-2">
+                    {format!("{} Stats", if self.show_stats { "▾" } else { "▸" })}
+                </button>
+                if self.show_stats {
+                    <dl class="grid grid-cols-2 gap-y-1">
+                        <dt>{"Messages"}</dt><dd class="text-right">{format_count(self.messages.len(), &self.locale)}</dd>
+                        <dt>{"Online users"}</dt><dd class="text-right">{format_count(self.users.len(), &self.locale)}</dd>
+                        <dt>{"Per minute"}</dt><dd class="text-right">{format_number(rate, &self.locale, 1)}</dd>
+                    </dl>
+                }
+            </section>
+        }
+    }
+
+    fn view_send_error(&self, ctx: &Context<Self>) -> Html {
+        let text = match &self.send_error {
+            None => return html! {},
+            Some(WsError::SendFailed) => {
+                "Too many messages are waiting to be sent. Try again in a moment.".to_string()
+            }
+            Some(WsError::Serialize(_)) => "That message couldn't be encoded.".to_string(),
+            Some(WsError::NotConnected) => {
+                "You're offline, so the message wasn't sent.".to_string()
+            }
+            Some(WsError::Closed(close)) => close.describe(),
+        };
+        html! {
+            <div role="alert" class="flex items-center justify-between bg-red-50 text-red-700 text-sm px-4 py-2">
+                <span>{text}</span>
+                <button onclick={ctx.link().callback(|_| Msg::DismissSendError)} title="Dismiss" class="ml-4 hover:text-red-900">{"✕"}</button>
+            </div>
+        }
+    }
+
+    fn view_failed(&self, ctx: &Context<Self>) -> Html {
+        if self.failed.is_empty() {
+            return html! {};
+        }
+        let count = self.failed.len();
+        let connected = self.connection == ConnectionState::Connected;
+        html! {
+            <div role="status" class="flex items-center justify-between bg-yellow-50 text-yellow-800 text-sm px-4 py-2">
+                <span>{if count == 1 { "1 message wasn't sent.".to_string() } else { format!("{} messages weren't sent.", count) }}</span>
+                <button onclick={ctx.link().callback(|_| Msg::ResendFailed)} disabled={!connected} title={(!connected).then_some("Waiting for the connection")} class="ml-4 font-bold hover:underline disabled:opacity-50 disabled:no-underline">
+                    {if count == 1 { "Resend 1 failed message".to_string() } else { format!("Resend {} failed messages", count) }}
+                </button>
+            </div>
+        }
+    }
+
+    fn view_connection(&self, ctx: &Context<Self>) -> Html {
+        match &self.connection {
+            ConnectionState::Reconnecting { attempt, max } => html! {
+                <div role="status" class="fixed top-4 left-1/2 transform -translate-x-1/2 z-40 flex items-center bg-gray-800 text-white text-sm rounded-full shadow-lg px-4 py-2 pointer-events-none">
+                    <span class="w-4 h-4 mr-2 rounded-full border-2 border-white border-t-transparent animate-spin motion-reduce:animate-none"></span>
+                    {format!("Reconnecting… (attempt {}/{})", attempt, max)}
+                </div>
+            },
+            ConnectionState::Disconnected(close) => html! {
+                <div role="alert" class="flex items-center justify-between bg-red-100 text-red-800 text-sm px-4 py-2">
+                    <span>{close.as_ref().map_or_else(|| "Disconnected from the chat server.".to_string(), CloseReason::describe)}</span>
+                    if close.as_ref().is_none_or(CloseReason::should_reconnect) {
+                        <button onclick={ctx.link().callback(|_| Msg::Reconnect)} class="font-bold hover:underline">{"Reconnect"}</button>
+                    } else {
+                        <button onclick={ctx.link().callback(|_| Msg::Logout)} class="font-bold hover:underline">{"Back to login"}</button>
+                    }
+                </div>
+            },
+            ConnectionState::Connecting
+            | ConnectionState::Connected
+            | ConnectionState::Unreachable => html! {},
+        }
+    }
+
+    /// Pulsing placeholders for the roster and message list before the first
+    /// roster arrives.
+    fn view_roster_skeleton(&self) -> Html {
+        html! {
+            <div class="animate-pulse motion-reduce:animate-none" aria-hidden="true">
+                {for (0..4).map(|i| html! {
+                    <div key={i} class="flex items-center mb-3">
+                        <div class={classes!("mr-2", "bg-gray-300", self.preferences.avatar_size.roster_class(), self.preferences.avatar_shape.class())}></div>
+                        <div class="flex-grow">
+                            <div class="h-3 w-24 mb-2 rounded bg-gray-300"></div>
+                            <div class="h-2 w-16 rounded bg-gray-200"></div>
+                        </div>
+                    </div>
+                })}
+            </div>
+        }
+    }
+
+    fn view_message_skeleton(&self) -> Html {
+        html! {
+            <div class="animate-pulse motion-reduce:animate-none" aria-hidden="true">
+                {for ["w-48", "w-72", "w-32", "w-60"].into_iter().map(|width| html! {
+                    <div key={width} class="flex items-end mb-4">
+                        <div class={classes!("mr-3", "flex-shrink-0", "bg-gray-300", self.preferences.avatar_size.message_class(), self.preferences.avatar_shape.class())}></div>
+                        <div class="bg-white rounded-lg p-3 shadow">
+                            <div class="h-3 w-20 mb-2 rounded bg-gray-300"></div>
+                            <div class={classes!("h-2", "rounded", "bg-gray-200", width)}></div>
+                        </div>
+                    </div>
+                })}
+            </div>
+        }
+    }
+
+    fn view_user(&self, ctx: &Context<Self>, user: &UserProfile) -> Html {
+        let id = user.id.clone();
+        let jump = ctx.link().callback(move |_| Msg::JumpToUser(id.clone()));
+        let status = match (&user.room, user.status) {
+            (_, Status::Away) => "Away".to_string(),
+            (Some(room), _) if *room != self.room => format!("In #{}", room),
+            _ => "Active now".to_string(),
+        };
+        if self.compact_roster {
+            let highlight = if self.pulsing.contains_key(&user.id) {
+                "bg-blue-100"
+            } else {
+                "hover:bg-gray-50"
+            };
+            let dot = if user.status == Status::Away {
+                "bg-yellow-400"
+            } else {
+                "bg-green-500"
+            };
+            return html! {
+                <div key={user.id.clone()} onclick={jump} title="Jump to latest message" class={classes!("flex", "items-center", "rounded", "px-2", "py-0.5", "text-sm", "cursor-pointer", "transition-colors", "duration-500", "motion-reduce:transition-none", highlight)}>
+                    <span role="img" title={status.clone()} aria-label={status.clone()} class={classes!("flex-shrink-0", "w-2", "h-2", "mr-2", "rounded-full", dot)}></span>
+                    <span class="truncate">{&user.name}</span>
+                </div>
+            };
+        }
+        html! {
+            <div key={user.id.clone()} onclick={jump} title="Jump to latest message" class={classes!("flex", "items-center", "rounded-lg", "p-2", "mb-2", "shadow", "cursor-pointer", "transition-colors", "duration-500", "motion-reduce:transition-none", if self.pulsing.contains_key(&user.id) { "bg-blue-100" } else { "bg-white hover:bg-gray-50" })}>
+                <img class={classes!("flex-shrink-0", "ring-2", presence_ring(Some(user.status)), self.preferences.avatar_size.roster_class(), self.preferences.avatar_shape.class())} src={user.avatar.clone()} alt={format!("{}'s avatar", user.name)} />
+                <div class="ml-4">
+                    <p class="text-sm font-medium">{&user.name}</p>
+                    <p class="text-xs text-gray-400">{status}</p>
+                </div>
+            </div>
+        }
+    }
+}
+
+impl Component for Chat {
+    type Message = Msg;
+    type Properties = ();
+
+    fn create(ctx: &Context<Self>) -> Self {
+        // `RequireLogin` only mounts the chat once a username is set.
+        let username = ctx
+            .link()
+            .context::<User>(Callback::noop())
+            .map(|(user, _)| user.username.borrow().clone())
+            .unwrap_or_default();
+        let preferences = Preferences::load();
+        let wss = Box::new(WebsocketService::new(
+            auth::load_token(),
+            preferences.reconnect,
+        ));
+
+        let (room, jump_to) =
+            current_hash_route().unwrap_or_else(|| (DEFAULT_ROOM.to_string(), None));
+        let hash_changed = ctx.link().callback(|_| Msg::HashChanged);
+        let hash_listener = EventListener::new(&gloo::utils::window(), "hashchange", move |e| {
+            hash_changed.emit(e.clone())
+        });
+        let activity_listeners = ["keydown", "focus"].map(|event| {
+            let activity = ctx.link().callback(|_| Msg::Activity);
+            EventListener::new(&gloo::utils::window(), event, move |e| {
+                activity.emit(e.clone())
+            })
+        });
+
+        let presence_listeners = [("focus", true), ("blur", false)].map(|(event, focused)| {
+            let focus = ctx.link().callback(move |_| Msg::WindowFocus(focused));
+            EventListener::new(&gloo::utils::window(), event, move |e| {
+                focus.emit(e.clone())
+            })
+        });
+
+        let help = ctx.link().callback(|_| Msg::ToggleShortcutHelp);
+        let cycle_room = ctx
+            .link()
+            .callback(|(step, unread)| Msg::CycleRoom(step, unread));
+        let viewport_listeners = match visual_viewport_target() {
+            Some(viewport) => ["resize", "scroll"]
+                .into_iter()
+                .map(|event| {
+                    let changed = ctx.link().callback(|_| Msg::ViewportChanged);
+                    EventListener::new(&viewport, event, move |_| changed.emit(()))
+                })
+                .collect(),
+            None => vec![],
+        };
+        // `pagehide` rather than `unload`, which mobile browsers often skip.
+        let save_scroll = ctx.link().callback(|_| Msg::SaveScrollPosition);
+        let unload_listener = EventListener::new(&gloo::utils::window(), "pagehide", move |_| {
+            save_scroll.emit(())
+        });
+        let shortcut_listener = EventListener::new(&gloo::utils::window(), "keydown", move |e| {
+            if let Some(e) = e.dyn_ref::<KeyboardEvent>() {
+                match e.key().as_str() {
+                    "?" if !is_typing_target(e) => help.emit(()),
+                    "ArrowUp" | "ArrowDown" if e.alt_key() => {
+                        e.prevent_default();
+                        let step = if e.key() == "ArrowUp" { -1 } else { 1 };
+                        cycle_room.emit((step, e.shift_key()));
+                    }
+                    _ => {}
+                }
+            }
+        });
+
+        let mut chat = Self {
+            user_id: username.clone(),
+            username,
+            users: vec![],
+            messages: vec![],
+            pending: vec![],
+            failed: vec![],
+            expanded: HashSet::new(),
+            seen: SeenKeys::default(),
+            last_seq: None,
+            next_local_id: 0,
+            chat_input: NodeRef::default(),
+            message_list: NodeRef::default(),
+            initial_loaded: false,
+            rendered_ids: HashSet::new(),
+            scroll_to_bottom: false,
+            saved_scroll: LocalStorage::get::<(String, i32)>(SCROLL_POSITION_KEY)
+                .ok()
+                .filter(|(saved_room, _)| *saved_room == room)
+                .map(|(_, top)| top),
+            scroll_to: None,
+            scroll_after_load: true,
+            wss,
+            long_polling: false,
+            connection: ConnectionState::Connecting,
+            send_error: None,
+            round_trips: Vec::new(),
+            failed_sends: Vec::new(),
+            _producer: EventBus::bridge(ctx.link().batch_callback(|event| match event {
+                Event::Frame(s) => Some(Msg::HandleMsg(s)),
+                Event::Connection(state) => Some(Msg::ConnectionChanged(state)),
+                Event::Toast(_) => None,
+            })),
+            rooms: vec![DEFAULT_ROOM.to_string(), room.clone()],
+            room,
+            draft: String::new(),
+            drafts: HashMap::new(),
+            typing: HashMap::new(),
+            pulsing: HashMap::new(),
+            queued_roster: None,
+            queued_presence: vec![],
+            roster_flush: None,
+            inbound: vec![],
+            inbound_frame: None,
+            mention: None,
+            mention_index: 0,
+            last_typing_sent: 0.0,
+            show_all_users: false,
+            roster_sort: RosterSort::Alphabetical,
+            roster_filter: String::new(),
+            last_read: HashMap::new(),
+            only_unread: false,
+            pinned: vec![],
+            show_pinned: true,
+            show_stats: false,
+            _hash_listener: hash_listener,
+            preferences,
+            show_settings: false,
+            show_shortcut_help: false,
+            pending_large_send: None,
+            show_gif_picker: false,
+            window_focused: true,
+            minimized: LocalStorage::get(MINIMIZED_KEY).unwrap_or(false),
+            sidebar_open: false,
+            focus_mode: LocalStorage::get(FOCUS_MODE_KEY)
+                .unwrap_or_else(|_| matches_media(NARROW_VIEWPORT_QUERY)),
+            compact_roster: LocalStorage::get(COMPACT_ROSTER_KEY).unwrap_or(false),
+            unread_while_minimized: 0,
+            lightbox: None,
+            locale: browser_locale(),
+            reporting: None,
+            forwarding: None,
+            reported: HashSet::new(),
+            read_only_rooms: HashSet::new(),
+            show_poll_dialog: false,
+            votes: HashMap::new(),
+            threads: HashMap::new(),
+            thread: None,
+            thread_draft: String::new(),
+            context_menu: None,
+            details: None,
+            idle_timer: None,
+            removed: None,
+            rejoin_wait: None,
+            logout_countdown: None,
+            highlight: None,
+            jump_to,
+            _activity_listeners: activity_listeners,
+            _presence_listeners: presence_listeners,
+            _shortcut_listener: shortcut_listener,
+            _unload_listener: unload_listener,
+            _pending_sweep: {
+                let expire = ctx.link().callback(|_| Msg::ExpirePending);
+                Interval::new(PENDING_SWEEP_MS, move || expire.emit(()))
+            },
+            visual_viewport: visual_viewport(),
+            _viewport_listeners: viewport_listeners,
+            on_quote: ctx.link().callback(Msg::Quote),
+            on_toggle_pin: ctx.link().callback(Msg::TogglePin),
+            on_open_image: ctx.link().callback(Msg::OpenLightbox),
+            on_context_menu: ctx
+                .link()
+                .callback(|(id, x, y)| Msg::OpenContextMenu(id, x, y)),
+            on_toggle_expanded: ctx.link().callback(Msg::ToggleExpanded),
+            on_vote: ctx.link().callback(|(id, option)| Msg::Vote(id, option)),
+            on_open_thread: ctx.link().callback(Msg::OpenThread),
+        };
+        chat.rooms.dedup();
+        chat.update_handshake();
+        chat.reset_idle_timer(ctx);
+        chat
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::HandleMsg(s) => {
+                // Frames this client can't read are skipped rather than
+                // taking the whole chat down; newer servers may send them.
+                let msg: WebSocketMessage = match serde_json::from_str(&s) {
+                    Ok(msg) => msg,
+                    Err(e) => {
+                        log_unparsable_frame(e);
+                        return false;
+                    }
+                };
+                match msg.message_type {
+                    MsgTypes::Users => {
+                        let roster = msg
+                            .data
+                            .and_then(|data| serde_json::from_str::<Vec<RosterEntry>>(&data).ok())
+                            .unwrap_or_else(|| {
+                                let names = msg.data_array.unwrap_or_default();
+                                names
+                                    .into_iter()
+                                    .map(|name| RosterEntry {
+                                        name,
+                                        ..Default::default()
+                                    })
+                                    .collect()
+                            });
+                        // The roster is complete, so it supersedes earlier
+                        // presence changes.
+                        self.queued_roster = Some(roster);
+                        self.queued_presence.clear();
+                        self.schedule_roster_flush(ctx);
+                        false
+                    }
+                    MsgTypes::Message => {
+                        let message_data: MessageData =
+                            match msg.data.as_deref().map(serde_json::from_str) {
+                                Some(Ok(message_data)) => message_data,
+                                Some(Err(e)) => {
+                                    log_unparsable_frame(e);
+                                    return false;
+                                }
+                                None => {
+                                    log_unparsable_frame("message frame without data");
+                                    return false;
+                                }
+                            };
+                        self.inbound.push(message_data);
+                        // Animation frames don't run in background tabs, so
+                        // don't hold messages back there.
+                        if gloo::utils::document().hidden() {
+                            return self.flush_inbound(ctx);
+                        }
+                        if self.inbound_frame.is_none() {
+                            let flush = ctx.link().callback(|_| Msg::FlushInbound);
+                            self.inbound_frame =
+                                Some(request_animation_frame(move |_| flush.emit(())));
+                        }
+                        false
+                    }
+                    MsgTypes::Presence => {
+                        let presence = msg
+                            .data
+                            .and_then(|data| serde_json::from_str::<PresenceData>(&data).ok());
+                        match presence {
+                            Some(p) => {
+                                let user_id = p.user_id.unwrap_or(p.from);
+                                self.queued_presence.push((user_id, p.status));
+                                self.schedule_roster_flush(ctx);
+                                false
+                            }
+                            None => false,
+                        }
+                    }
+                    MsgTypes::Typing => {
+                        let typing = msg
+                            .data
+                            .and_then(|data| serde_json::from_str::<TypingData>(&data).ok());
+                        match typing {
+                            Some(t) => {
+                                let user_id = t.user_id.unwrap_or_else(|| t.from.clone());
+                                if user_id == self.user_id {
+                                    return false;
+                                }
+                                let expired = ctx.link().callback(Msg::TypingExpired);
+                                let id = user_id.clone();
+                                let typer = Typer {
+                                    name: t.from,
+                                    room: msg.room.unwrap_or_else(|| DEFAULT_ROOM.to_string()),
+                                    _expiry: Timeout::new(TYPING_TIMEOUT_MS, move || {
+                                        expired.emit(id)
+                                    }),
+                                };
+                                self.touch_user(&user_id);
+                                self.typing.insert(user_id, typer);
+                                true
+                            }
+                            None => false,
+                        }
+                    }
+                    MsgTypes::Pin | MsgTypes::Unpin => match msg.data {
+                        Some(id) => self.set_pinned(id, matches!(msg.message_type, MsgTypes::Pin)),
+                        None => false,
+                    },
+                    MsgTypes::Register => {
+                        if let Some(user_id) = msg.data {
+                            self.user_id = user_id;
+                        }
+                        false
+                    }
+                    MsgTypes::Kicked => {
+                        let reason = msg.data.unwrap_or_default();
+                        self.remove(
+                            ctx,
+                            CloseReason {
+                                code: CloseReason::KICKED,
+                                reason,
+                            },
+                        );
+                        true
+                    }
+                    MsgTypes::Report => {
+                        if msg.data.is_some_and(|id| self.reported.contains(&id)) {
+                            show_toast(
+                                "Thanks, the moderators will take a look",
+                                ToastLevel::Success,
+                            );
+                        }
+                        false
+                    }
+                    MsgTypes::Vote => {
+                        let vote = msg
+                            .data
+                            .and_then(|data| serde_json::from_str::<VoteData>(&data).ok());
+                        match vote {
+                            Some(VoteData {
+                                poll_id,
+                                option,
+                                user_id: Some(user_id),
+                            }) => self.set_vote(poll_id, user_id, option),
+                            _ => false,
+                        }
+                    }
+                    MsgTypes::Edit => {
+                        let edit = msg
+                            .data
+                            .and_then(|data| serde_json::from_str::<EditData>(&data).ok());
+                        let edit = match edit {
+                            Some(edit) => edit,
+                            None => return false,
+                        };
+                        let at = edit.edited_at.unwrap_or_else(|| js_sys::Date::now() as i64);
+                        self.messages
+                            .iter_mut()
+                            .find(|m| m.id() == edit.message_id)
+                            .is_some_and(|m| m.apply_edit(edit.message, at))
+                    }
+                    MsgTypes::Room => {
+                        let data = msg
+                            .data
+                            .and_then(|data| serde_json::from_str::<RoomData>(&data).ok());
+                        match (msg.room, data) {
+                            (Some(room), Some(data)) => {
+                                if data.read_only {
+                                    self.read_only_rooms.insert(room)
+                                } else {
+                                    self.read_only_rooms.remove(&room)
+                                }
+                            }
+                            _ => false,
+                        }
+                    }
+                    MsgTypes::Unknown => {
+                        log_unknown_frame(msg.version);
+                        false
+                    }
+                    _ => false,
+                }
+            }
+            Msg::ConnectionChanged(state) => {
+                // Proxies that block WebSockets make the very first attempt
+                // fail; try plain HTTP before declaring the server down.
+                if state == ConnectionState::Unreachable && !self.long_polling {
+                    log::info!(target: "ws", "event=fallback transport=long_poll");
+                    self.wss = Box::new(LongPollService::new(auth::load_token()));
+                    self.long_polling = true;
+                    self.update_handshake();
+                    return false;
+                }
+                if let ConnectionState::Disconnected(Some(close)) = &state {
+                    if close.code == CloseReason::UNAUTHORIZED {
+                        auth::clear_token();
+                        show_toast(close.describe(), ToastLevel::Error);
+                        self.logout(ctx);
+                        return false;
+                    }
+                    if matches!(close.code, CloseReason::KICKED | CloseReason::SERVER_FULL) {
+                        self.remove(ctx, close.clone());
+                        return true;
+                    }
+                }
+                if self.removed.is_some() {
+                    return false;
+                }
+                self.connection = state;
+                true
+            }
+            Msg::Reconnect => {
+                self.removed = None;
+                self.rejoin_wait = None;
+                self.wss = Box::new(WebsocketService::new(
+                    auth::load_token(),
+                    self.preferences.reconnect,
+                ));
+                self.long_polling = false;
+                self.connection = ConnectionState::Connecting;
+                self.update_handshake();
+                true
+            }
+            Msg::RejoinAvailable => {
+                self.rejoin_wait = None;
+                true
+            }
+            Msg::Logout => {
+                self.logout(ctx);
+                false
+            }
+            Msg::SubmitMessage => {
+                let input = self.chat_input.cast::<HtmlTextAreaElement>();
+                if let Some(input) = input {
+                    let text = input.value();
+                    if let Some(command) = text.strip_prefix('/') {
+                        if self.run_command(command) {
+                            return true;
+                        }
+                    }
+                    if text.chars().count() > LARGE_MESSAGE_CHARS {
+                        self.pending_large_send = Some(text);
+                        return true;
+                    }
+                    self.send_chat_message(text, false, None);
+                };
+                self.reset_idle_timer(ctx);
+                true
+            }
+            Msg::SendAsCode => {
+                if self.draft.trim().is_empty() {
+                    return false;
+                }
+                let text = fence_text(&self.draft);
+                if text.chars().count() > LARGE_MESSAGE_CHARS {
+                    self.pending_large_send = Some(text);
+                    return true;
+                }
+                self.send_chat_message(text, false, None);
+                self.reset_idle_timer(ctx);
+                true
+            }
+            Msg::ConfirmLargeSend => {
+                if let Some(text) = self.pending_large_send.take() {
+                    self.send_chat_message(text, false, None);
+                }
+                self.reset_idle_timer(ctx);
+                true
+            }
+            Msg::CancelSend => {
+                self.pending_large_send = None;
+                true
+            }
+            Msg::Quote(text) => {
+                let quoted = quote_text(&text);
+                let cursor = quoted.encode_utf16().count() as u32;
+                self.set_input(format!("{}{}", quoted, self.draft));
+                if let Some(input) = self.chat_input.cast::<HtmlTextAreaElement>() {
+                    let _ = input.focus();
+                    let _ = input.set_selection_range(cursor, cursor);
+                }
+                false
+            }
+            Msg::ApplyFormat(format) => {
+                if let Some(input) = self.chat_input.cast::<HtmlTextAreaElement>() {
+                    let start = input.selection_start().ok().flatten().unwrap_or(0);
+                    let end = input.selection_end().ok().flatten().unwrap_or(start);
+                    let (text, start, end) = wrap_selection(&self.draft, start, end, format);
+                    self.set_input(text);
+                    let _ = input.focus();
+                    let _ = input.set_selection_range(start, end);
+                }
+                false
+            }
+            Msg::TogglePin(id) => {
+                let pin = !self.pinned.contains(&id);
+                self.send(&WebSocketMessage {
+                    message_type: if pin { MsgTypes::Pin } else { MsgTypes::Unpin },
+                    data: Some(id.clone()),
+                    data_array: None,
+                    room: Some(self.room.clone()),
+                    version: PROTOCOL_VERSION,
+                    ephemeral: false,
+                    reply_to: None,
+                    forwarded_from: None,
+                });
+                self.set_pinned(id, pin)
+            }
+            Msg::TogglePinnedPanel => {
+                self.show_pinned = !self.show_pinned;
+                true
+            }
+            Msg::ToggleStats => {
+                self.show_stats = !self.show_stats;
+                true
+            }
+            Msg::InputChanged(text) => {
+                let now = js_sys::Date::now();
+                if !text.is_empty() && now - self.last_typing_sent > TYPING_THROTTLE_MS {
+                    self.last_typing_sent = now;
+                    self.send(&WebSocketMessage {
+                        message_type: MsgTypes::Typing,
+                        data: None,
+                        data_array: None,
+                        room: Some(self.room.clone()),
+                        version: PROTOCOL_VERSION,
+                        ephemeral: false,
+                        reply_to: None,
+                        forwarded_from: None,
+                    });
+                }
+                let cursor = self
+                    .chat_input
+                    .cast::<HtmlTextAreaElement>()
+                    .and_then(|input| input.selection_start().ok().flatten())
+                    .map_or(text.len(), |offset| utf16_to_byte(&text, offset));
+                let mention = mention_at(&text, cursor).map(|(at, query)| (at, query.to_string()));
+                let changed = mention != self.mention;
+                if changed {
+                    self.mention = mention;
+                    self.mention_index = 0;
+                }
+                self.draft = text;
+                changed
+            }
+            Msg::MoveMentionSelection(delta) => {
+                let count = self.mention_suggestions().len();
+                if count == 0 {
+                    return false;
+                }
+                self.mention_index =
+                    (self.mention_index as isize + delta).rem_euclid(count as isize) as usize;
+                true
+            }
+            Msg::CompleteMention => {
+                let name = self.mention_suggestions().get(self.mention_index).cloned();
+                if let (Some((at, query)), Some(name)) = (self.mention.take(), name) {
+                    let end = at + 1 + query.len();
+                    let inserted = format!("@{} ", name);
+                    let cursor =
+                        self.draft[..at].encode_utf16().count() + inserted.encode_utf16().count();
+                    let text = format!("{}{}{}", &self.draft[..at], inserted, &self.draft[end..]);
+                    self.set_input(text);
+                    if let Some(input) = self.chat_input.cast::<HtmlTextAreaElement>() {
+                        let _ = input.set_selection_range(cursor as u32, cursor as u32);
+                    }
+                }
+                true
+            }
+            Msg::DismissMention => self.mention.take().is_some(),
+            Msg::TypingExpired(user_id) => self.typing.remove(&user_id).is_some(),
+            Msg::PulseEnded(user_id) => self.pulsing.remove(&user_id).is_some(),
+            Msg::FlushInbound => {
+                self.inbound_frame = None;
+                self.flush_inbound(ctx)
+            }
+            Msg::FlushRoster => {
+                self.roster_flush = None;
+                let mut changed = false;
+                if let Some(roster) = self.queued_roster.take() {
+                    self.apply_roster(roster);
+                    changed = true;
+                }
+                for (user_id, status) in std::mem::take(&mut self.queued_presence) {
+                    changed |= self.set_user_status(&user_id, status);
+                }
+                changed
+            }
+            Msg::DismissSendError => {
+                self.send_error = None;
+                true
+            }
+            Msg::ResendFailed => {
+                self.resend_failed();
+                self.scroll_to_bottom = true;
+                true
+            }
+            Msg::ExpirePending => self.expire_pending(),
+            Msg::ToggleGifPicker => {
+                self.show_gif_picker = !self.show_gif_picker;
+                true
+            }
+            Msg::SendGif(url) => {
+                self.show_gif_picker = false;
+                if !self.can_post() {
+                    return true;
+                }
+                self.scroll_to_bottom = true;
+                self.send(&WebSocketMessage {
+                    message_type: MsgTypes::Message,
+                    data: Some(url),
+                    data_array: None,
+                    room: Some(self.room.clone()),
+                    version: PROTOCOL_VERSION,
+                    ephemeral: false,
+                    reply_to: None,
+                    forwarded_from: None,
+                });
+                self.reset_idle_timer(ctx);
+                true
+            }
+            Msg::SaveScrollPosition => {
+                if let Some(list) = self.message_list.cast::<web_sys::Element>() {
+                    let position = (self.room.clone(), list.scroll_top());
+                    if let Err(e) = LocalStorage::set(SCROLL_POSITION_KEY, position) {
+                        log::error!("failed to save scroll position: {:?}", e);
+                    }
+                }
+                false
+            }
+            Msg::ViewportChanged => {
+                let viewport = visual_viewport();
+                let changed = viewport != self.visual_viewport;
+                self.visual_viewport = viewport;
+                changed
+            }
+            Msg::HashChanged => match current_hash_route() {
+                Some((room, message)) => {
+                    self.jump_to = message;
+                    if room != self.room {
+                        self.sidebar_open = false;
+                        self.join_room(room);
+                        return true;
+                    }
+                    self.jump_to.is_some()
+                }
+                None => false,
+            },
+            Msg::CopyInviteLink => {
+                match copy_to_clipboard(&page_link(&room_hash(&self.room))) {
+                    Ok(()) => show_toast("Invite link copied", ToastLevel::Success),
+                    Err(e) => {
+                        log::error!("failed to copy invite link: {:?}", e);
+                        show_toast("Couldn't copy the invite link", ToastLevel::Error);
+                    }
+                }
+                false
+            }
+            Msg::JumpToMessage(id) => {
+                self.highlight = highlight_message(&id);
+                false
+            }
+            Msg::JumpToUser(user_id) => {
+                let latest = self
+                    .messages
+                    .iter()
+                    .rev()
+                    .find(|m| m.room() == self.room && m.user_id() == user_id);
+                if let Some(message) = latest {
+                    self.highlight = highlight_message(message.id());
+                }
+                false
+            }
+            Msg::ToggleAllUsers => {
+                self.show_all_users = !self.show_all_users;
+                true
+            }
+            Msg::SetRosterSort(sort) => {
+                self.roster_sort = sort;
+                true
+            }
+            Msg::FilterRoster(query) => {
+                self.roster_filter = query;
+                true
+            }
+            Msg::RefreshRoster => {
+                // The server answers with a regular `users` frame.
+                self.send(&WebSocketMessage {
+                    message_type: MsgTypes::Users,
+                    data: None,
+                    data_array: None,
+                    room: None,
+                    version: PROTOCOL_VERSION,
+                    ephemeral: false,
+                    reply_to: None,
+                    forwarded_from: None,
+                });
+                false
+            }
+            Msg::ToggleUnreadOnly => {
+                self.only_unread = !self.only_unread;
+                true
+            }
+            Msg::WindowFocus(focused) => {
+                self.window_focused = focused;
+                if !focused {
+                    self.mark_room_read();
+                }
+                self.set_presence(if focused {
+                    Status::Online
+                } else {
+                    Status::Away
+                });
+                true
+            }
+            Msg::Activity => {
+                // While the warning is up only "Stay" keeps the session alive.
+                if self.logout_countdown.is_none() {
+                    self.reset_idle_timer(ctx);
+                }
+                false
+            }
+            Msg::IdleTimeout => {
+                let tick = ctx.link().callback(|_| Msg::LogoutTick);
+                let interval = Interval::new(1_000, move || tick.emit(()));
+                self.logout_countdown = Some((LOGOUT_WARNING_SECS, interval));
+                self.set_presence(Status::Away);
+                true
+            }
+            Msg::LogoutTick => match &mut self.logout_countdown {
+                Some((secs, _)) if *secs > 1 => {
+                    *secs -= 1;
+                    true
+                }
+                Some(_) => {
+                    self.logout(ctx);
+                    false
+                }
+                None => false,
+            },
+            Msg::StayLoggedIn => {
+                self.logout_countdown = None;
+                self.set_presence(Status::Online);
+                self.reset_idle_timer(ctx);
+                true
+            }
+            Msg::ToggleSidebar => {
+                self.sidebar_open = !self.sidebar_open;
+                true
+            }
+            Msg::ToggleFocusMode => {
+                self.focus_mode = !self.focus_mode;
+                if let Err(e) = LocalStorage::set(FOCUS_MODE_KEY, self.focus_mode) {
+                    log::error!("failed to save focus mode: {:?}", e);
+                }
+                true
+            }
+            Msg::ToggleCompactRoster => {
+                self.compact_roster = !self.compact_roster;
+                if let Err(e) = LocalStorage::set(COMPACT_ROSTER_KEY, self.compact_roster) {
+                    log::error!("failed to save roster mode: {:?}", e);
+                }
+                true
+            }
+            Msg::ToggleMinimize => {
+                self.minimized = !self.minimized;
+                self.unread_while_minimized = 0;
+                if let Err(e) = LocalStorage::set(MINIMIZED_KEY, self.minimized) {
+                    log::error!("failed to save minimized state: {:?}", e);
+                }
+                true
+            }
+            Msg::OpenLightbox(url) => {
+                self.lightbox = Some(url);
+                true
+            }
+            Msg::CloseLightbox => {
+                self.lightbox = None;
+                true
+            }
+            Msg::OpenContextMenu(id, x, y) => {
+                self.context_menu = Some((id, x, y));
+                true
+            }
+            Msg::ToggleExpanded(id) => {
+                if !self.expanded.remove(&id) {
+                    self.expanded.insert(id);
+                }
+                true
+            }
+            Msg::CloseContextMenu => self.context_menu.take().is_some(),
+            Msg::ShowDetails(id) => {
+                self.details = Some(id);
+                true
+            }
+            Msg::CloseDetails => self.details.take().is_some(),
+            Msg::OpenThread(id) => {
+                if self.thread.as_ref() != Some(&id) {
+                    self.thread_draft.clear();
+                }
+                self.thread = Some(id);
+                true
+            }
+            Msg::CloseThread => self.thread.take().is_some(),
+            Msg::ThreadInputChanged(text) => {
+                self.thread_draft = text;
+                true
+            }
+            Msg::SubmitThreadReply => {
+                let text = self.thread_draft.trim_end().to_string();
+                if text.trim().is_empty() {
+                    return false;
+                }
+                self.send_chat_message(text, false, self.thread.clone());
+                self.reset_idle_timer(ctx);
+                true
+            }
+            Msg::OpenReport(id) => {
+                self.reporting = Some(id);
+                true
+            }
+            Msg::CloseReport => self.reporting.take().is_some(),
+            Msg::OpenForward(id) => {
+                self.forwarding = Some(id);
+                true
+            }
+            Msg::CloseForward => self.forwarding.take().is_some(),
+            Msg::Forward(room) => {
+                let message = self
+                    .forwarding
+                    .take()
+                    .and_then(|id| self.messages.iter().find(|m| m.id() == id));
+                let message = match message {
+                    Some(message) => message,
+                    None => return true,
+                };
+                // Forwarding a forward keeps crediting the original sender.
+                let forward = PendingMessage {
+                    text: message.message.clone(),
+                    room: room.clone(),
+                    ephemeral: false,
+                    reply_to: None,
+                    forwarded_from: Some(
+                        message
+                            .forwarded_from
+                            .clone()
+                            .unwrap_or_else(|| message.from.clone()),
+                    ),
+                    sent_at: js_sys::Date::now(),
+                };
+                if !self.post(forward) {
+                    show_toast(
+                        format!("Couldn't forward to #{} right now", room),
+                        ToastLevel::Error,
+                    );
+                } else if self.send_error.is_none() {
+                    show_toast(format!("Forwarded to #{}", room), ToastLevel::Success);
+                }
+                true
+            }
+            Msg::TogglePollDialog => {
+                self.show_poll_dialog = !self.show_poll_dialog;
+                true
+            }
+            Msg::CreatePoll(question, options) => {
+                self.show_poll_dialog = false;
+                if !self.can_post() {
+                    return true;
+                }
+                let poll = PollData {
+                    question: &question,
+                    options: &options,
+                };
+                self.send(&WebSocketMessage {
+                    message_type: MsgTypes::Poll,
+                    data: serde_json::to_string(&poll).ok(),
+                    data_array: None,
+                    room: Some(self.room.clone()),
+                    version: PROTOCOL_VERSION,
+                    ephemeral: false,
+                    reply_to: None,
+                    forwarded_from: None,
+                });
+                self.scroll_to_bottom = true;
+                true
+            }
+            Msg::Vote(poll_id, option) => {
+                let mine = self
+                    .votes
+                    .get(&poll_id)
+                    .and_then(|votes| votes.get(&self.user_id));
+                let option = (mine != Some(&option)).then_some(option);
+                let vote = VoteData {
+                    poll_id: poll_id.clone(),
+                    option,
+                    user_id: None,
+                };
+                self.send(&WebSocketMessage {
+                    message_type: MsgTypes::Vote,
+                    data: serde_json::to_string(&vote).ok(),
+                    data_array: None,
+                    room: Some(self.room.clone()),
+                    version: PROTOCOL_VERSION,
+                    ephemeral: false,
+                    reply_to: None,
+                    forwarded_from: None,
+                });
+                // The relayed vote will set the same entry again.
+                self.send_error.is_none() && self.set_vote(poll_id, self.user_id.clone(), option)
+            }
+            Msg::SubmitReport(reason) => {
+                let id = match self.reporting.take() {
+                    Some(id) => id,
+                    None => return false,
+                };
+                let report = ReportData {
+                    message_id: &id,
+                    reason,
+                };
+                self.send(&WebSocketMessage {
+                    message_type: MsgTypes::Report,
+                    data: serde_json::to_string(&report).ok(),
+                    data_array: None,
+                    room: Some(self.room.clone()),
+                    version: PROTOCOL_VERSION,
+                    ephemeral: false,
+                    reply_to: None,
+                    forwarded_from: None,
+                });
+                if self.send_error.is_none() {
+                    self.reported.insert(id);
+                }
+                true
+            }
+            Msg::CopyMessage(id) => {
+                if let Some(message) = self.messages.iter().find(|m| m.id() == id) {
+                    match copy_to_clipboard(&message.message) {
+                        Ok(()) => show_toast("Message copied", ToastLevel::Success),
+                        Err(e) => {
+                            log::error!("failed to copy message: {:?}", e);
+                            show_toast("Couldn't copy the message", ToastLevel::Error);
+                        }
+                    }
+                }
+                false
+            }
+            Msg::CopyMessageLink(id) => {
+                if let Some(message) = self.messages.iter().find(|m| m.id() == id) {
+                    match copy_to_clipboard(&page_link(&message_hash(message.room(), &id))) {
+                        Ok(()) => show_toast("Link copied", ToastLevel::Success),
+                        Err(e) => {
+                            log::error!("failed to copy message link: {:?}", e);
+                            show_toast("Couldn't copy the link", ToastLevel::Error);
+                        }
+                    }
+                }
+                false
+            }
+            Msg::ToggleSettings => {
+                self.show_settings = !self.show_settings;
+                true
+            }
+            Msg::ToggleShortcutHelp => {
+                self.show_shortcut_help = !self.show_shortcut_help;
+                true
+            }
+            Msg::CycleRoom(step, unread) => {
+                let current = self.rooms.iter().position(|r| *r == self.room).unwrap_or(0);
+                let target = cycle(self.rooms.len(), current, step)
+                    .map(|i| &self.rooms[i])
+                    .find(|room| !unread || self.unread_in(room) > 0);
+                // Same path as clicking the room in the sidebar.
+                if let Some(room) = target {
+                    let _ = gloo::utils::window().location().set_hash(&room_hash(room));
+                }
+                false
+            }
+            Msg::UpdatePreferences(preferences) => {
+                preferences.save();
+                let avatar_changed = preferences.avatar_set != self.preferences.avatar_set;
+                self.preferences = preferences;
+                if avatar_changed {
+                    let avatar = avatar_url(&self.user_id, self.preferences.avatar_set);
+                    if let Some(me) = self.users.iter_mut().find(|u| u.id == self.user_id) {
+                        me.avatar = avatar;
+                    }
+                    self.send(&self.profile_message());
+                    self.update_handshake();
+                }
+                self.reset_idle_timer(ctx);
+                true
+            }
+        }
+    }
+
+    fn rendered(&mut self, _ctx: &Context<Self>, _first_render: bool) {
+        for message in &self.messages {
+            if !self.rendered_ids.contains(message.id()) {
+                self.rendered_ids.insert(message.id().to_string());
+            }
+        }
+        if std::mem::take(&mut self.scroll_to_bottom) {
+            if let Some(list) = self.message_list.cast::<web_sys::Element>() {
+                list.set_scroll_top(list.scroll_height());
+            }
+        }
+        if let Some(top) = self.scroll_to.take() {
+            if let Some(list) = self.message_list.cast::<web_sys::Element>() {
+                list.set_scroll_top(top);
+            }
+        }
+        // Wait for history to bring in the message a permalink points at.
+        let arrived = self
+            .jump_to
+            .as_ref()
+            .filter(|id| self.messages.iter().any(|m| m.id() == *id));
+        if let Some(id) = arrived {
+            self.highlight = highlight_message(id);
+            self.jump_to = None;
+        }
+    }
+
+    #[allow(clippy::unnecessary_operation)]
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        if let Some(close) = &self.removed {
+            return self.view_removed(ctx, close);
+        }
+        if self.connection == ConnectionState::Unreachable {
+            return self.view_unreachable(ctx);
+        }
+        if self.minimized {
+            return self.view_minimized(ctx);
+        }
+        let submit = ctx.link().callback(|_| Msg::SubmitMessage);
+        let oninput = ctx.link().callback(|e: InputEvent| {
+            let input: HtmlTextAreaElement = e.target_unchecked_into();
+            Msg::InputChanged(input.value())
+        });
+        let suggestions = self.mention_suggestions();
+        let suggesting = !suggestions.is_empty();
+        let mut attachments = vec![];
+        if gif::API_KEY.is_some() {
+            attachments.push(MenuItem {
+                label: "GIF",
+                on_select: ctx.link().callback(|_| Msg::ToggleGifPicker),
+            });
+        }
+        attachments.push(MenuItem {
+            label: "Poll",
+            on_select: ctx.link().callback(|_| Msg::TogglePollDialog),
+        });
+        let send_key = self.preferences.send_key;
+        let onkeydown = ctx.link().batch_callback(move |e: KeyboardEvent| {
+            if e.is_composing() {
+                return None;
+            }
+            let msg = match e.key().as_str() {
+                "ArrowDown" if suggesting => Msg::MoveMentionSelection(1),
+                "ArrowUp" if suggesting => Msg::MoveMentionSelection(-1),
+                "Tab" | "Enter" if suggesting => Msg::CompleteMention,
+                "Escape" if suggesting => Msg::DismissMention,
+                "Enter" if send_key.sends(e.ctrl_key() || e.meta_key(), e.shift_key()) => {
+                    Msg::SubmitMessage
+                }
+                _ => return None,
+            };
+            e.prevent_default();
+            Some(msg)
+        });
+        let copy_invite = ctx.link().callback(|_| Msg::CopyInviteLink);
+        let toggle_settings = ctx.link().callback(|_| Msg::ToggleSettings);
+        let read = if self.only_unread {
+            self.last_read.get(&self.room).map(String::as_str)
+        } else {
+            None
+        };
+        let users = self.sorted_users();
+        // On a struggling connection, skip the entrance animation.
+        let quality = self.quality();
+        let fade = self.preferences.fade_old_messages;
+        let group_window_ms = i64::from(self.preferences.group_window_secs) * 1_000;
+        let can_post = self.can_post();
+        let now = js_sys::Date::now() as i64;
+        let room_messages: Vec<&MessageData> = unread(&self.messages, &self.room, read).collect();
+
+        html! {
+        // Sized to the visual viewport so the composer stays above an
+        // on-screen keyboard even where the browser doesn't resize the page.
+        <div style={self.visual_viewport.map(|(height, top)| format!("height: {}px; transform: translateY({}px)", height, top))} class="flex h-[100dvh] w-full overflow-hidden">
+            if self.sidebar_open {
+                <div onclick={ctx.link().callback(|_| Msg::ToggleSidebar)} class="fixed inset-0 z-30 bg-black bg-opacity-40 md:hidden"></div>
+            }
+            if self.focus_mode && !self.sidebar_open {
+                <aside class="hidden md:flex w-12 flex-shrink-0 flex-col items-center bg-gray-100 py-4">
+                    <button onclick={ctx.link().callback(|_| Msg::ToggleFocusMode)} title="Show sidebar" class="text-xl text-gray-500 hover:text-gray-800">{"»"}</button>
+                </aside>
+            } else {
+                <aside class={classes!("w-64", "flex-shrink-0", "bg-gray-100", "p-4", "overflow-y-auto", if self.sidebar_open { "fixed inset-y-0 left-0 z-40 shadow-lg md:static md:shadow-none" } else { "hidden md:block" })}>
+                    <div class="flex justify-end -mt-2 mb-2">
+                        <button onclick={ctx.link().callback(|_| Msg::ToggleFocusMode)} title="Focus mode: hide sidebar" class="text-xl text-gray-500 hover:text-gray-800">{"«"}</button>
+                    </div>
+                    <h2 class="text-xl font-bold mb-4">{"Rooms"}</h2>
+                    <nav class="mb-6">
+                        {for self.rooms.iter().map(|room| {
+                            let class = if *room == self.room {
+                                "block rounded-lg px-3 py-1 mb-1 bg-blue-600 text-white"
+                            } else {
+                                "block rounded-lg px-3 py-1 mb-1 hover:bg-gray-200"
+                            };
+                            html!{
+                                <a key={room.clone()} {class} href={room_hash(room)}>{format!("# {}", room)}</a>
+                            }
+                        })}
+                    </nav>
+                    <div class="flex items-center justify-between mb-4">
+                        <h2 class="text-xl font-bold">{format!("In #{}", self.room)}</h2>
+                        <div class="flex items-center">
+                            <button onclick={ctx.link().callback(|_| Msg::ToggleCompactRoster)} title={if self.compact_roster { "Show avatars" } else { "Compact list" }} aria-pressed={self.compact_roster.to_string()} class="mr-2 text-lg text-gray-500 hover:text-gray-800">{"☰"}</button>
+                            <button onclick={ctx.link().callback(|_| Msg::RefreshRoster)} title="Refresh online users" class="text-lg text-gray-500 hover:text-gray-800">{"↻"}</button>
+                        </div>
+                    </div>
+                    <input type="search" value={self.roster_filter.clone()} oninput={ctx.link().callback(|e: InputEvent| Msg::FilterRoster(e.target_unchecked_into::<HtmlInputElement>().value()))} placeholder="Find a user" aria-label="Find a user" class="w-full mb-3 rounded-lg border border-gray-300 px-2 py-1 text-sm" />
+                    <div class="flex mb-3 text-xs">
+                        {for [(RosterSort::Alphabetical, "A–Z"), (RosterSort::RecentActivity, "Recent activity")].into_iter().map(|(sort, label)| {
+                            let class = if sort == self.roster_sort {
+                                "mr-1 px-2 py-1 rounded-full bg-gray-700 text-white"
+                            } else {
+                                "mr-1 px-2 py-1 rounded-full text-gray-600 hover:bg-gray-200"
+                            };
+                            html! {
+                                <button onclick={ctx.link().callback(move |_| Msg::SetRosterSort(sort))} {class}>{label}</button>
+                            }
+                        })}
+                    </div>
+                    if self.initial_loaded {
+                        {for users.iter().filter(|u| u.in_room(&self.room)).map(|user| self.view_user(ctx, user))}
+                    } else {
+                        {self.view_roster_skeleton()}
+                    }
+                    <button onclick={ctx.link().callback(|_| Msg::ToggleAllUsers)} class="w-full text-left text-sm font-bold text-gray-600 mt-4 mb-2">
+                        {format!("{} All online users ({})", if self.show_all_users { "▾" } else { "▸" }, format_count(self.users.len(), &self.locale))}
+                    </button>
+                    if self.show_all_users {
+                        {for users.iter().map(|user| self.view_user(ctx, user))}
+                    }
+                    {self.view_stats(ctx)}
+                </aside>
+            }
+            <main class="flex-grow min-w-0 flex flex-col bg-gray-50">
+                {self.view_connection(ctx)}
+                <header class="flex flex-wrap items-center justify-between gap-2 bg-white shadow p-4">
+                    <div class="flex items-center min-w-0">
+                        <button onclick={ctx.link().callback(|_| Msg::ToggleSidebar)} title="Rooms and users" class="md:hidden mr-3 text-2xl text-gray-500 hover:text-gray-800">{"☰"}</button>
+                        <h1 class="text-xl font-bold truncate">{format!("💬 #{}", self.room)}</h1>
+                        if self.connection == ConnectionState::Connected {
+                            {view_quality(quality)}
+                        }
+                    </div>
+                    <div class="flex items-center">
+                        <label class="flex items-center mr-4 text-sm text-gray-600">
+                            <input type="checkbox" checked={self.only_unread} onchange={ctx.link().callback(|_| Msg::ToggleUnreadOnly)} class="mr-1" />
+                            {"Unread only"}
+                        </label>
+                        <button onclick={copy_invite} class="text-sm text-blue-600 hover:underline">{"Copy invite link"}</button>
+                        <button onclick={toggle_settings} title="Settings" class="ml-4 text-xl text-gray-500 hover:text-gray-800">{"⚙"}</button>
+                        <button onclick={ctx.link().callback(|_| Msg::ToggleMinimize)} title="Minimize" class="ml-4 text-xl text-gray-500 hover:text-gray-800">{"—"}</button>
+                    </div>
+                </header>
+                {self.view_pinned(ctx)}
+                <div ref={self.message_list.clone()} class="flex-grow overflow-auto p-4">
+                    if !self.initial_loaded && room_messages.is_empty() {
+                        {self.view_message_skeleton()}
+                    }
+                    if self.only_unread && room_messages.is_empty() {
+                        <p class="text-center text-sm text-gray-400">{"No unread messages."}</p>
+                    }
+                    {for room_messages.iter().copied().enumerate().flat_map(|(i, message)| {
+                        let previous = i.checked_sub(1).map(|i| room_messages[i]);
+                        let header = starts_group(previous, message, group_window_ms).then(|| html! {
+                            <p key={format!("time-{}", message.id())} class="text-center text-xs text-gray-400 mb-2 select-none">
+                                {message.timestamp.map(|t| format_timestamp(t, self.preferences.time_format, &self.locale)).unwrap_or_default()}
+                            </p>
+                        });
+                        // The sender may have left since; fall back to their own
+                        // avatar, ringed as offline.
+                        let sender = self.users.iter().find(|u| u.id == message.user_id());
+                        let avatar = sender.map_or_else(|| avatar_url(message.user_id(), self.avatar_set_for(message.user_id())), |u| u.avatar.clone());
+                        let item = html!{
+                            <MessageItem
+                                key={message.id().to_string()}
+                                message={message.clone()}
+                                {avatar}
+                                avatar_class={classes!(self.preferences.avatar_size.message_class(), self.preferences.avatar_shape.class(), "ring-2", presence_ring(sender.map(|u| u.status)))}
+                                fade_class={classes!(message.timestamp.filter(|_| fade).map(|t| fade_class(now - t)))}
+                                can_pin={self.can_pin()}
+                                pinned={self.pinned.iter().any(|p| p == message.id())}
+                                reported={self.reported.contains(message.id())}
+                                animate={quality == ConnectionQuality::Good && !self.rendered_ids.contains(message.id())}
+                                on_quote={self.on_quote.clone()}
+                                on_toggle_pin={self.on_toggle_pin.clone()}
+                                on_open_image={self.on_open_image.clone()}
+                                on_context_menu={self.on_context_menu.clone()}
+                                expanded={self.expanded.contains(message.id())}
+                                on_toggle_expanded={self.on_toggle_expanded.clone()}
+                                poll={self.votes.get(message.id()).map(|votes| tally_votes(votes, &self.user_id)).unwrap_or_default()}
+                                on_vote={self.on_vote.clone()}
+                                replies={self.threads.get(message.id()).map_or(0, Vec::len)}
+                                edit_times={message.edit_history.iter().map(|(_, t)| format_timestamp(*t, self.preferences.time_format, &self.locale)).collect::<Vec<_>>()}
+                                on_open_thread={self.on_open_thread.clone()}
+                                time_format={self.preferences.time_format}
+                                locale={self.locale.clone()}
+                            />
+                        };
+                        header.into_iter().chain(std::iter::once(item))
+                    })}
+                    {for self.pending.iter().filter(|p| p.room == self.room && p.reply_to.is_none()).map(|p| html! {
+                        <div class="flex items-center justify-end mb-4 opacity-60">
+                            <div class="bg-white rounded-lg p-3 shadow text-gray-600 text-xs whitespace-pre-wrap">{&p.text}</div>
+                            <span title="Sending…" class="w-4 h-4 ml-2 rounded-full border-2 border-gray-400 border-t-transparent animate-spin motion-reduce:animate-none"></span>
+                        </div>
+                    })}
+                    {for self.failed.iter().filter(|p| p.room == self.room).map(|p| html! {
+                        <div class="flex items-center justify-end mb-4">
+                            <div class="bg-white rounded-lg p-3 shadow border border-red-200 text-gray-600 text-xs whitespace-pre-wrap">{&p.text}</div>
+                            <span title="Not sent" class="ml-2 text-red-500">{"!"}</span>
+                        </div>
+                    })}
+                </div>
+                {self.view_typing()}
+                {self.view_backlog()}
+                {self.view_send_error(ctx)}
+                {self.view_failed(ctx)}
+                <div class="flex bg-white border-t border-gray-100 px-4 pt-2 text-gray-500">
+                    {for [(Format::Bold, "B", "Bold", "font-bold"), (Format::Italic, "I", "Italic", "italic"), (Format::Code, "</>", "Code", "font-mono")].into_iter().map(|(format, label, title, class)| html! {
+                        <button onclick={ctx.link().callback(move |_| Msg::ApplyFormat(format))} {title} class={classes!("w-8", "h-8", "mr-1", "rounded", "text-sm", "hover:bg-gray-100", "hover:text-gray-800", class)}>{label}</button>
+                    })}
+                    <button onclick={ctx.link().callback(|_| Msg::SendAsCode)} disabled={self.draft.trim().is_empty() || self.backed_up() || !can_post} title="Send the whole message as a code block" class="ml-auto h-8 px-2 rounded text-xs font-mono hover:bg-gray-100 hover:text-gray-800 disabled:opacity-50">{"Send as code"}</button>
+                </div>
+                <footer class="relative flex items-center p-4 bg-white shadow">
+                    if suggesting {
+                        <ul role="listbox" class="absolute bottom-full left-4 mb-2 w-56 bg-white rounded-lg shadow-lg py-1 text-sm z-10">
+                            {for suggestions.iter().enumerate().map(|(i, name)| {
+                                let class = if i == self.mention_index { "px-3 py-1 bg-blue-600 text-white" } else { "px-3 py-1" };
+                                html! {
+                                    <li key={name.clone()} role="option" aria-selected={(i == self.mention_index).to_string()} {class}>{format!("@{}", name)}</li>
+                                }
+                            })}
+                        </ul>
+                    }
+                    if !attachments.is_empty() && can_post {
+                        <AttachMenu items={attachments} />
+                    }
+                    if let Some(api_key) = gif::API_KEY.filter(|_| self.show_gif_picker) {
+                        <GifPicker {api_key} on_select={ctx.link().callback(Msg::SendGif)} on_close={ctx.link().callback(|_| Msg::ToggleGifPicker)} />
+                    }
+                    <textarea ref={self.chat_input.clone()} {oninput} {onkeydown} disabled={!can_post} spellcheck={if self.preferences.spellcheck { "true" } else { "false" }} rows="1" placeholder={if can_post { "Type a message..." } else { "Only admins can post here" }} class="flex-grow resize-none rounded-2xl border-2 border-gray-300 p-2 mr-2 focus:border-blue-500 outline-none disabled:bg-gray-100 disabled:cursor-not-allowed"></textarea>
+                    <button onclick={submit} disabled={self.backed_up() || !can_post} title={self.backed_up().then_some("Waiting for the server")} class="flex justify-center items-center w-12 h-12 text-white bg-blue-600 rounded-full hover:bg-blue-700 focus:outline-none disabled:opacity-50">
+                        <svg class="w-6 h-6" fill="none" stroke="currentColor" viewBox="0 0 24 24" xmlns="http://www.w3.org/2000/svg"><path stroke-linecap="round" stroke-linejoin="round" stroke-width="2" d="M5 13l4 4L19 7"></path></svg>
+                    </button>
+                </footer>
+            </main>
+            {self.view_thread(ctx)}
+            if self.show_settings {
+                <Settings
+                    preferences={self.preferences.clone()}
+                    user_id={self.user_id.clone()}
+                    on_change={ctx.link().callback(Msg::UpdatePreferences)}
+                    on_close={ctx.link().callback(|_| Msg::ToggleSettings)}
+                />
+            }
+            if let Some(text) = &self.pending_large_send {
+                <Modal title="Send a long message?" on_close={ctx.link().callback(|_| Msg::CancelSend)}>
+                    <p class="mb-4">{format!("Send {}-character message?", format_count(text.chars().count(), &self.locale))}</p>
+                    <div class="flex justify-end">
+                        <button onclick={ctx.link().callback(|_| Msg::CancelSend)} class="px-4 py-2 mr-2 rounded-lg hover:bg-gray-100">{"Cancel"}</button>
+                        <button onclick={ctx.link().callback(|_| Msg::ConfirmLargeSend)} class="px-4 py-2 rounded-lg bg-blue-600 text-white hover:bg-blue-700">{"Send"}</button>
+                    </div>
+                </Modal>
+            }
+            {self.view_context_menu(ctx)}
+            {self.view_details(ctx)}
+            {self.view_forward(ctx)}
+            if self.show_shortcut_help {
+                <ShortcutHelp send_key={self.preferences.send_key} on_close={ctx.link().callback(|_| Msg::ToggleShortcutHelp)} />
+            }
+            if self.show_poll_dialog {
+                <PollDialog on_submit={ctx.link().callback(|(question, options)| Msg::CreatePoll(question, options))} on_close={ctx.link().callback(|_| Msg::TogglePollDialog)} />
+            }
+            if self.reporting.is_some() {
+                <ReportDialog on_submit={ctx.link().callback(Msg::SubmitReport)} on_close={ctx.link().callback(|_| Msg::CloseReport)} />
+            }
+            if let Some(url) = &self.lightbox {
+                <Lightbox url={url.clone()} on_close={ctx.link().callback(|_| Msg::CloseLightbox)} />
+            }
+            {self.view_logout_warning(ctx)}
+        </div>
+    }
+    }
+}
+
+/// Where a message numbered `seq` goes in `messages` to keep numbered
+/// messages in order, or `None` if one with that number is already there.
+/// Unnumbered messages go at the end. Scans back from the end, since messages
+/// nearly always arrive in order.
+fn insert_position(messages: &[MessageData], seq: Option<u64>) -> Option<usize> {
+    let seq = match seq {
+        Some(seq) => seq,
+        None => return Some(messages.len()),
+    };
+    let mut index = messages.len();
+    for (i, message) in messages.iter().enumerate().rev() {
+        match message.seq {
+            Some(other) if other == seq => return None,
+            Some(other) if other < seq => break,
+            Some(_) => index = i,
+            None => {}
+        }
+    }
+    Some(index)
+}
+
+/// Where `message` goes in `messages`, or `None` if it repeats one already
+/// there, e.g. when history is replayed after a reconnect. Admitted messages
+/// are recorded in `seen`.
+fn admit(messages: &[MessageData], seen: &mut SeenKeys, message: &MessageData) -> Option<usize> {
+    let index = insert_position(messages, message.seq)?;
+    // Without an id or timestamp two identical messages are legitimately
+    // distinct, so only messages carrying one of them are de-duplicated.
+    let keyed = message.id.is_some() || message.timestamp.is_some();
+    (!keyed || seen.insert(dedup_key(message))).then_some(index)
+}
+
+/// Dedup keys of the most recent messages, forgetting the oldest beyond
+/// [`MAX_SEEN_KEYS`].
+#[derive(Default)]
+struct SeenKeys {
+    keys: HashSet<String>,
+    order: VecDeque<String>,
+}
+
+impl SeenKeys {
+    /// Records `key`, returning whether it was new.
+    fn insert(&mut self, key: String) -> bool {
+        if !self.keys.insert(key.clone()) {
+            return false;
+        }
+        self.order.push_back(key);
+        if self.order.len() > MAX_SEEN_KEYS {
+            if let Some(oldest) = self.order.pop_front() {
+                self.keys.remove(&oldest);
+            }
+        }
+        true
+    }
+}
+
+/// Messages in `room` after the one with id `last_read`, or all of them when
+/// nothing was read yet. Thread replies are left to the thread panel.
+fn unread<'a>(
+    messages: &'a [MessageData],
+    room: &'a str,
+    last_read: Option<&str>,
+) -> impl Iterator<Item = &'a MessageData> {
+    let start = last_read
+        .and_then(|id| messages.iter().position(|m| m.id() == id))
+        .map_or(0, |i| i + 1);
+    messages[start..]
+        .iter()
+        .filter(move |m| m.room() == room && m.reply_to.is_none())
+}
+
+/// Stable identity of a message: the server id when present, otherwise a hash
+/// of its sender, text and timestamp.
+fn dedup_key(msg: &MessageData) -> String {
+    if let Some(id) = &msg.id {
+        return id.clone();
+    }
+    let mut hasher = DefaultHasher::new();
+    msg.from.hash(&mut hasher);
+    msg.message.hash(&mut hasher);
+    msg.timestamp.hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+/// Whether `message` starts a new cluster after `previous`, and so gets a time
+/// header: the sender changed or more than `window_ms` passed.
+fn starts_group(previous: Option<&MessageData>, message: &MessageData, window_ms: i64) -> bool {
+    let previous = match previous {
+        Some(previous) => previous,
+        None => return true,
+    };
+    previous.user_id() != message.user_id()
+        || match (previous.timestamp, message.timestamp) {
+            (Some(before), Some(after)) => after - before > window_ms,
+            _ => false,
+        }
+}
+
+/// Messages per minute over the minute before `now` (milliseconds since the
+/// epoch).
+fn messages_per_minute(msgs: &[MessageData], now: i64) -> f64 {
+    msgs.iter()
+        .filter_map(|m| m.timestamp)
+        .filter(|&t| t <= now && now - t < 60_000)
+        .count() as f64
+}
+
+/// Opacity class for a message `age_ms` old: fully opaque for the first two
+/// hours, then a step fainter every few hours down to half after a day.
+fn fade_class(age_ms: i64) -> &'static str {
+    const HOUR_MS: i64 = 60 * 60_000;
+    match age_ms / HOUR_MS {
+        ..=1 => "",
+        2..=5 => "opacity-90",
+        6..=11 => "opacity-75",
+        12..=23 => "opacity-60",
+        _ => "opacity-50",
+    }
+}
+
+/// Formats a timestamp as a local time of day with `Intl.DateTimeFormat`, so
+/// the output follows the browser's locale, e.g. `12:34 PM` or `12:34`.
+fn format_timestamp(timestamp: i64, format: TimeFormat, locale: &str) -> String {
+    intl_format(
+        timestamp,
+        &[("hour", "numeric"), ("minute", "2-digit")],
+        format,
+        locale,
+    )
+}
+
+/// Formats a timestamp as a full local date and time, e.g. `Tuesday, 3 March
+/// 2026 at 12:34:56`, for when the time of day alone isn't enough.
+pub fn format_full_timestamp(timestamp: i64, format: TimeFormat, locale: &str) -> String {
+    intl_format(
+        timestamp,
+        &[("dateStyle", "full"), ("timeStyle", "medium")],
+        format,
+        locale,
+    )
+}
+
+/// Formats a timestamp with `Intl.DateTimeFormat` and the given options,
+/// honouring the 12/24-hour preference.
+fn intl_format(
+    timestamp: i64,
+    fields: &[(&str, &str)],
+    format: TimeFormat,
+    locale: &str,
+) -> String {
+    let options = js_sys::Object::new();
+    let set = |key: &str, value: JsValue| {
+        let _ = js_sys::Reflect::set(&options, &key.into(), &value);
+    };
+    for (key, value) in fields {
+        set(key, (*value).into());
+    }
+    if let Some(hour12) = format.hour12() {
+        set("hour12", hour12.into());
+    }
+    let formatter =
+        js_sys::Intl::DateTimeFormat::new(&js_sys::Array::of1(&locale.into()), &options);
+    let date = js_sys::Date::new(&(timestamp as f64).into());
+    formatter
+        .format()
+        .call1(&JsValue::UNDEFINED, &date)
+        .ok()
+        .and_then(|s| s.as_string())
+        .unwrap_or_default()
+}
+
+/// Counts the votes on a poll, given each voter's choice. Choices past
+/// [`MAX_OPTIONS`] can't be shown and are skipped.
+fn tally_votes(votes: &HashMap<String, usize>, user_id: &str) -> PollTally {
+    let mut counts = vec![];
+    for &option in votes.values().filter(|&&option| option < MAX_OPTIONS) {
+        if counts.len() <= option {
+            counts.resize(option + 1, 0);
+        }
+        counts[option] += 1;
+    }
+    PollTally {
+        counts,
+        mine: votes.get(user_id).copied(),
+    }
+}
+
+/// Scrolls a message into view and flashes its background so the eye can find
+/// it. The returned timer ends the flash; dropping it leaves the highlight
+/// class in place until the next jump.
+fn highlight_message(id: &str) -> Option<Timeout> {
+    let element = match gloo::utils::document().get_element_by_id(&message_dom_id(id)) {
+        Some(element) => element,
+        None => {
+            log::debug!("message {} is not rendered", id);
+            return None;
+        }
+    };
+    element.scroll_into_view_with_scroll_into_view_options(
+        ScrollIntoViewOptions::new()
+            .behavior(if matches_media(REDUCED_MOTION_QUERY) {
+                ScrollBehavior::Auto
+            } else {
+                ScrollBehavior::Smooth
+            })
+            .block(ScrollLogicalPosition::Center),
+    );
+    // Restart the animation if the message is still flashing from a previous
+    // jump; reading the layout in between makes the browser notice.
+    let classes = element.class_list();
+    let _ = classes.remove_1("animate-flash");
+    element.client_width();
+    let _ = classes.add_1("animate-flash");
+    Some(Timeout::new(HIGHLIGHT_MS, move || {
+        let _ = element.class_list().remove_1("animate-flash");
+    }))
+}
+
+/// `window.visualViewport`, where supported. Read through `Reflect` as the
+/// web-sys version in use has no bindings for it.
+fn visual_viewport_target() -> Option<web_sys::EventTarget> {
+    js_sys::Reflect::get(&gloo::utils::window(), &"visualViewport".into())
+        .ok()?
+        .dyn_into()
+        .ok()
+}
+
+/// Height and offset from the top of the layout viewport of the part of the
+/// page actually visible, if the browser supports `visualViewport`.
+fn visual_viewport() -> Option<(f64, f64)> {
+    let viewport = visual_viewport_target()?;
+    let number = |key: &str| js_sys::Reflect::get(&viewport, &key.into()).ok()?.as_f64();
+    Some((number("height")?, number("offsetTop")?))
+}
+
+fn matches_media(query: &str) -> bool {
+    gloo::utils::window()
+        .match_media(query)
+        .ok()
+        .flatten()
+        .is_some_and(|query| query.matches())
+}
+
+/// Indices of a list of `len` items other than `from`, in the order reached by
+/// repeatedly stepping `step` from it and wrapping around at either end.
+fn cycle(len: usize, from: usize, step: isize) -> impl Iterator<Item = usize> {
+    let len = len as isize;
+    (1..len).map(move |i| (from as isize + i * step).rem_euclid(len) as usize)
+}
+
+/// Fragment that deep-links to `room`, e.g. `#/room/general`.
+fn room_hash(room: &str) -> String {
+    format!("#/room/{}", js_sys::encode_uri_component(room))
+}
+
+/// Fragment that links to a message, e.g. `#/room/general/msg/abc123`.
+fn message_hash(room: &str, id: &str) -> String {
+    format!(
+        "{}/msg/{}",
+        room_hash(room),
+        js_sys::encode_uri_component(id)
+    )
+}
+
+/// Parses a fragment made by [`room_hash`] or [`message_hash`] back into the
+/// room name and, for the latter, the message id.
+fn parse_room_hash(hash: &str) -> Option<(String, Option<String>)> {
+    let rest = hash.strip_prefix("#/room/")?;
+    let decode = |encoded| js_sys::decode_uri_component(encoded).ok()?.as_string();
+    let (room, message) = match rest.split_once("/msg/") {
+        Some((room, id)) => (decode(room)?, decode(id).filter(|id| !id.is_empty())),
+        None => (decode(rest)?, None),
+    };
+    (!room.is_empty()).then_some((room, message))
+}
+
+fn current_hash_route() -> Option<(String, Option<String>)> {
+    let hash = gloo::utils::window().location().hash().ok()?;
+    parse_room_hash(&hash)
+}
+
+/// Absolute URL of the current page with the fragment `hash`.
+fn page_link(hash: &str) -> String {
+    let location = gloo::utils::window().location();
+    format!(
+        "{}{}{}",
+        location.origin().unwrap_or_default(),
+        location.pathname().unwrap_or_default(),
+        hash
+    )
+}
+
+/// Finds an `@mention` being typed just before byte offset `cursor`: an `@`
+/// at the start or after whitespace, followed by non-whitespace up to the
+/// cursor. Returns the offset of the `@` and the text after it.
+fn mention_at(text: &str, cursor: usize) -> Option<(usize, &str)> {
+    let before = text.get(..cursor)?;
+    let at = before.rfind('@')?;
+    let query = &before[at + 1..];
+    let starts_word = before[..at]
+        .chars()
+        .next_back()
+        .is_none_or(char::is_whitespace);
+    (starts_word && !query.contains(char::is_whitespace)).then_some((at, query))
+}
+
+/// Whether `name` contains `query`, ignoring case. Everything matches an
+/// empty query.
+fn name_contains(name: &str, query: &str) -> bool {
+    name.to_lowercase().contains(&query.trim().to_lowercase())
+}
+
+/// Tailwind ring colour showing presence around an avatar: green when online,
+/// yellow when away and grey for users not in the roster.
+fn presence_ring(status: Option<Status>) -> &'static str {
+    match status {
+        Some(Status::Online) => "ring-green-500",
+        Some(Status::Away) => "ring-yellow-400",
+        None => "ring-gray-300",
+    }
+}
+
+/// Names of users whose name starts with `prefix`, ignoring case, in
+/// alphabetical order and without duplicates.
+fn match_users(prefix: &str, users: &[UserProfile]) -> Vec<String> {
+    let prefix = prefix.to_lowercase();
+    let mut names: Vec<String> = users
+        .iter()
+        .filter(|u| u.name.to_lowercase().starts_with(&prefix))
+        .map(|u| u.name.clone())
+        .collect();
+    names.sort_by_key(|name| name.to_lowercase());
+    names.dedup();
+    names.truncate(MAX_MENTION_SUGGESTIONS);
+    names
+}
+
+/// Appends to a bounded list of recent samples, dropping the oldest.
+fn push_sample<T>(samples: &mut Vec<T>, sample: T) {
+    if samples.len() == QUALITY_SAMPLES {
+        samples.remove(0);
+    }
+    samples.push(sample);
+}
+
+fn view_quality(quality: ConnectionQuality) -> Html {
+    let color = match quality {
+        ConnectionQuality::Good => "bg-green-500",
+        ConnectionQuality::Degraded => "bg-yellow-400",
+        ConnectionQuality::Poor => "bg-red-500",
+    };
+    html! {
+        <span role="img" title={format!("Connection: {}", quality.label())} aria-label={format!("Connection: {}", quality.label())} class={classes!("ml-3", "flex-shrink-0", "w-2", "h-2", "rounded-full", color)}></span>
+    }
+}
+
+/// Describes who is typing, naming at most two people, e.g. `Alice, Bob, and
+/// 8 others are typing…`. Empty when nobody is.
+fn typing_summary(names: &[String]) -> String {
+    match names {
+        [] => String::new(),
+        [one] => format!("{} is typing…", one),
+        [first, second] => format!("{} and {} are typing…", first, second),
+        [first, second, rest @ ..] => format!(
+            "{}, {}, and {} {} are typing…",
+            first,
+            second,
+            rest.len(),
+            if rest.len() == 1 { "other" } else { "others" }
+        ),
+    }
+}
+
+/// The browser's preferred locale, e.g. `de-CH`.
+fn browser_locale() -> String {
+    gloo::utils::window()
+        .navigator()
+        .language()
+        .unwrap_or_else(|| "en-US".to_string())
+}
+
+/// Formats a count the way `locale` writes numbers, e.g. `1,432` in `en-US`
+/// and `1.432` in `de-DE`.
+fn format_count(n: usize, locale: &str) -> String {
+    format_number(n as f64, locale, 0)
+}
+
+fn format_number(n: f64, locale: &str, max_fraction_digits: u32) -> String {
+    let options = js_sys::Object::new();
+    let _ = js_sys::Reflect::set(
+        &options,
+        &"maximumFractionDigits".into(),
+        &max_fraction_digits.into(),
+    );
+    let formatter = js_sys::Intl::NumberFormat::new(&js_sys::Array::of1(&locale.into()), &options);
+    formatter
+        .format()
+        .call1(&JsValue::UNDEFINED, &n.into())
+        .ok()
+        .and_then(|s| s.as_string())
+        .unwrap_or_else(|| n.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{
+        admit, cycle, fade_class, insert_position, match_users, mention_at, messages_per_minute,
+        name_contains, starts_group, tally_votes, typing_summary, unread, MessageData, Role,
+        SeenKeys, Status, UserProfile, MAX_SEEN_KEYS,
+    };
+
+    fn message(from: &str, timestamp: Option<i64>, seq: Option<u64>) -> MessageData {
+        MessageData {
+            from: from.into(),
+            message: "hi".into(),
+            id: None,
+            timestamp,
+            room: None,
+            user_id: None,
+            metadata: None,
+            bot: false,
+            ephemeral: false,
+            seq,
+            reply_to: None,
+            forwarded_from: None,
+            edited_at: None,
+            edit_history: Vec::new(),
+        }
+    }
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    #[test]
+    fn typing_summary_nobody() {
+        assert_eq!(typing_summary(&[]), "");
+    }
+
+    #[test]
+    fn typing_summary_one() {
+        assert_eq!(typing_summary(&names(&["Alice"])), "Alice is typing…");
+    }
+
+    #[test]
+    fn typing_summary_two() {
+        assert_eq!(
+            typing_summary(&names(&["Alice", "Bob"])),
+            "Alice and Bob are typing…"
+        );
+    }
+
+    #[test]
+    fn typing_summary_three() {
+        assert_eq!(
+            typing_summary(&names(&["Alice", "Bob", "Carol"])),
+            "Alice, Bob, and 1 other are typing…"
+        );
+    }
+
+    #[test]
+    fn typing_summary_many() {
+        let many: Vec<String> = (0..10).map(|i| format!("user{}", i)).collect();
+        assert_eq!(
+            typing_summary(&many),
+            "user0, user1, and 8 others are typing…"
+        );
+    }
+
+    fn user(name: &str) -> UserProfile {
+        UserProfile {
+            id: name.to_string(),
+            name: name.to_string(),
+            avatar: String::new(),
+            room: None,
+            status: Status::Online,
+            role: Role::Member,
+            last_active: 0.0,
+        }
+    }
+
+    #[test]
+    fn match_users_by_prefix() {
+        let users = [user("bob"), user("Alice"), user("alan"), user("carol")];
+        assert_eq!(match_users("al", &users), ["alan", "Alice"]);
+        assert_eq!(match_users("", &users).len(), 4);
+        assert!(match_users("z", &users).is_empty());
+    }
+
+    #[test]
+    fn filters_names_by_substring() {
+        assert!(name_contains("Alice", "LIC"));
+        assert!(name_contains("Alice", ""));
+        assert!(name_contains("Alice", " ali "));
+        assert!(!name_contains("Alice", "bob"));
+    }
+
+    #[test]
+    fn mention_at_cursor() {
+        assert_eq!(mention_at("hi @al", 6), Some((3, "al")));
+        assert_eq!(mention_at("@", 1), Some((0, "")));
+        assert_eq!(mention_at("mail@example", 12), None);
+        assert_eq!(mention_at("@al there", 9), None);
+    }
+
+    #[test]
+    fn messages_per_minute_counts_last_minute() {
+        let at = |timestamp| message("a", timestamp, None);
+        let msgs = [
+            at(Some(0)),
+            at(Some(50_000)),
+            at(None),
+            at(Some(90_000)),
+            at(Some(100_000)),
+        ];
+        assert_eq!(messages_per_minute(&msgs, 100_000), 3.0);
+        assert_eq!(messages_per_minute(&[], 100_000), 0.0);
+    }
+
+    #[test]
+    fn fade_class_steps_with_age() {
+        const HOUR_MS: i64 = 60 * 60_000;
+        assert_eq!(fade_class(0), "");
+        assert_eq!(fade_class(2 * HOUR_MS - 1), "");
+        assert_eq!(fade_class(2 * HOUR_MS), "opacity-90");
+        assert_eq!(fade_class(18 * HOUR_MS), "opacity-60");
+        assert_eq!(fade_class(72 * HOUR_MS), "opacity-50");
+    }
+
+    #[test]
+    fn tally_counts_each_voter_once() {
+        let votes: HashMap<String, usize> = [("a", 1), ("b", 1), ("c", 3), ("e", usize::MAX)]
+            .map(|(user, option)| (user.to_string(), option))
+            .into();
+        let tally = tally_votes(&votes, "b");
+        assert_eq!(tally.counts, [0, 2, 0, 1]);
+        assert_eq!(tally.mine, Some(1));
+        assert_eq!(tally_votes(&votes, "d").mine, None);
+    }
+
+    #[test]
+    fn groups_within_window() {
+        let at = |from, timestamp| message(from, Some(timestamp), None);
+        assert!(starts_group(None, &at("a", 0), 60_000));
+        assert!(!starts_group(Some(&at("a", 0)), &at("a", 60_000), 60_000));
+        assert!(starts_group(Some(&at("a", 0)), &at("a", 60_001), 60_000));
+        assert!(starts_group(Some(&at("a", 0)), &at("b", 1), 60_000));
+    }
+
+    #[test]
+    fn cycle_wraps_both_ways() {
+        assert_eq!(cycle(4, 1, 1).collect::<Vec<_>>(), [2, 3, 0]);
+        assert_eq!(cycle(4, 1, -1).collect::<Vec<_>>(), [0, 3, 2]);
+        assert_eq!(cycle(1, 0, 1).count(), 0);
+    }
+
+    #[test]
+    fn inserts_in_sequence_order() {
+        let numbered = |seq| message("a", None, seq);
+        let messages = [
+            numbered(Some(1)),
+            numbered(Some(5)),
+            numbered(None),
+            numbered(Some(6)),
+        ];
+        assert_eq!(insert_position(&messages, Some(7)), Some(4));
+        assert_eq!(insert_position(&messages, Some(3)), Some(1));
+        assert_eq!(insert_position(&messages, Some(0)), Some(0));
+        assert_eq!(insert_position(&messages, None), Some(4));
+        assert_eq!(insert_position(&messages, Some(5)), None);
+    }
+
+    #[test]
+    fn edits_keep_earlier_versions() {
+        let mut msg = MessageData {
+            message: "helo".into(),
+            ..message("a", Some(1_000), None)
+        };
+        assert!(!msg.edited());
+        assert!(msg.apply_edit("hello".into(), 2_000));
+        assert!(!msg.apply_edit("hello".into(), 2_500));
+        assert!(msg.apply_edit("hello!".into(), 3_000));
+        assert_eq!(msg.message, "hello!");
+        assert_eq!(
+            msg.edit_history,
+            [("helo".to_string(), 1_000), ("hello".to_string(), 2_000)]
+        );
+        assert!(msg.edited());
+    }
+
+    #[test]
+    fn reconnect_replay_keeps_order_and_unread() {
+        let numbered = |id: &str, seq| MessageData {
+            message: format!("message {}", id),
+            id: Some(id.into()),
+            ..message("a", Some(1_000), seq)
+        };
+        let receive =
+            |messages: &mut Vec<MessageData>, seen: &mut SeenKeys, batch: Vec<MessageData>| {
+                for m in batch {
+                    if let Some(index) = admit(messages, seen, &m) {
+                        messages.insert(index, m);
+                    }
+                }
+            };
+        let history = || {
+            vec![
+                numbered("1", Some(1)),
+                numbered("2", Some(2)),
+                numbered("3", None),
+            ]
+        };
+        let mut messages = vec![];
+        let mut seen = SeenKeys::default();
+        receive(&mut messages, &mut seen, history());
+        let read = messages.last().map(|m| m.id().to_string());
+
+        // The server replays its history after the reconnect, now with one
+        // message and one thread reply that arrived in between.
+        let mut replay = history();
+        replay.insert(2, numbered("4", Some(3)));
+        replay.push(MessageData {
+            reply_to: Some("1".into()),
+            ..numbered("5", None)
+        });
+        receive(&mut messages, &mut seen, replay);
+
+        let ids: Vec<&str> = messages.iter().map(MessageData::id).collect();
+        assert_eq!(ids, ["1", "2", "3", "4", "5"]);
+        let unread: Vec<&str> = unread(&messages, "general", read.as_deref())
+            .map(MessageData::id)
+            .collect();
+        assert_eq!(unread, ["4"]);
+    }
+
+    #[test]
+    fn seen_keys_forget_the_oldest_beyond_the_cap() {
+        let mut seen = SeenKeys::default();
+        for i in 0..=MAX_SEEN_KEYS {
+            assert!(seen.insert(i.to_string()));
+        }
+        assert!(!seen.insert(MAX_SEEN_KEYS.to_string()));
+        assert!(seen.insert("0".into()));
     }
 }
-fn generate_avatar_for_user(user_name: &str) -> String {
-    format!("https://robohash.org/{}.png?set=set4", user_name)
-}
\ No newline at end of file
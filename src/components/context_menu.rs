@@ -0,0 +1,71 @@
+use gloo::events::EventListener;
+use wasm_bindgen::JsCast;
+use web_sys::KeyboardEvent;
+use yew::prelude::*;
+
+#[derive(Clone, PartialEq)]
+pub struct MenuItem {
+    pub label: &'static str,
+    pub on_select: Callback<()>,
+}
+
+#[derive(Properties, PartialEq)]
+pub struct ContextMenuProps {
+    /// Viewport coordinates of the click that opened the menu.
+    pub x: i32,
+    pub y: i32,
+    pub items: Vec<MenuItem>,
+    pub on_close: Callback<()>,
+}
+
+/// Menu opened at the cursor. Selecting an item, clicking outside or pressing
+/// Escape closes it.
+#[function_component(ContextMenu)]
+pub fn context_menu(props: &ContextMenuProps) -> Html {
+    {
+        let on_close = props.on_close.clone();
+        use_effect_with_deps(
+            move |_| {
+                let listener = EventListener::new(&gloo::utils::window(), "keydown", move |e| {
+                    let escape = e
+                        .dyn_ref::<KeyboardEvent>()
+                        .is_some_and(|e| e.key() == "Escape");
+                    if escape {
+                        on_close.emit(());
+                    }
+                });
+                move || drop(listener)
+            },
+            (),
+        );
+    }
+    let close = props.on_close.reform(|_: MouseEvent| ());
+    let close_on_right_click = {
+        let on_close = props.on_close.clone();
+        Callback::from(move |e: MouseEvent| {
+            e.prevent_default();
+            on_close.emit(());
+        })
+    };
+
+    html! {
+        <>
+        <div onclick={close} oncontextmenu={close_on_right_click} class="fixed inset-0 z-40"></div>
+        <ul role="menu" style={format!("left: {}px; top: {}px", props.x, props.y)} class="fixed z-50 min-w-32 bg-white rounded-lg shadow-lg py-1 text-sm">
+            {for props.items.iter().map(|item| {
+                let select = {
+                    let on_select = item.on_select.clone();
+                    let on_close = props.on_close.clone();
+                    Callback::from(move |_: MouseEvent| {
+                        on_select.emit(());
+                        on_close.emit(());
+                    })
+                };
+                html! {
+                    <li role="menuitem" onclick={select} class="px-4 py-1 cursor-pointer hover:bg-gray-100">{item.label}</li>
+                }
+            })}
+        </ul>
+        </>
+    }
+}
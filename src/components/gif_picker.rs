@@ -0,0 +1,113 @@
+use gloo::timers::callback::Timeout;
+use wasm_bindgen_futures::spawn_local;
+use web_sys::HtmlInputElement;
+use yew::prelude::*;
+
+use crate::services::gif::{self, Gif};
+
+/// Milliseconds to wait after the last keystroke before searching.
+const SEARCH_DEBOUNCE_MS: u32 = 300;
+
+pub enum Msg {
+    QueryChanged(String),
+    Search(String),
+    Results(String, Vec<Gif>),
+}
+
+#[derive(Properties, PartialEq)]
+pub struct GifPickerProps {
+    pub api_key: &'static str,
+    pub on_select: Callback<String>,
+    pub on_close: Callback<()>,
+}
+
+pub struct GifPicker {
+    query: String,
+    results: Vec<Gif>,
+    loading: bool,
+    debounce: Option<Timeout>,
+}
+
+impl Component for GifPicker {
+    type Message = Msg;
+    type Properties = GifPickerProps;
+
+    fn create(_ctx: &Context<Self>) -> Self {
+        Self {
+            query: String::new(),
+            results: vec![],
+            loading: false,
+            debounce: None,
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::QueryChanged(query) => {
+                self.query = query.clone();
+                let search = ctx.link().callback(Msg::Search);
+                self.debounce = Some(Timeout::new(SEARCH_DEBOUNCE_MS, move || search.emit(query)));
+                false
+            }
+            Msg::Search(query) => {
+                if query.trim().is_empty() {
+                    self.results.clear();
+                    self.loading = false;
+                    return true;
+                }
+                self.loading = true;
+                let key = ctx.props().api_key;
+                let link = ctx.link().clone();
+                spawn_local(async move {
+                    match gif::search(key, &query).await {
+                        Ok(gifs) => link.send_message(Msg::Results(query, gifs)),
+                        Err(e) => {
+                            log::error!("gif search failed: {:?}", e);
+                            link.send_message(Msg::Results(query, vec![]));
+                        }
+                    }
+                });
+                true
+            }
+            Msg::Results(query, gifs) => {
+                // Drop responses to searches the user has already typed past.
+                if query != self.query {
+                    return false;
+                }
+                self.loading = false;
+                self.results = gifs;
+                true
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let oninput = ctx.link().callback(|e: InputEvent| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            Msg::QueryChanged(input.value())
+        });
+        let close = ctx.props().on_close.reform(|_: MouseEvent| ());
+
+        html! {
+            <div class="absolute bottom-20 right-4 z-40 w-80 bg-white rounded-lg shadow-lg p-3">
+                <div class="flex items-center mb-2">
+                    <input {oninput} type="search" placeholder="Search GIFs..." class="flex-grow rounded-full border-2 border-gray-300 px-3 py-1 focus:border-blue-500 outline-none" />
+                    <button onclick={close} class="ml-2 text-gray-500 hover:text-gray-800">{"✕"}</button>
+                </div>
+                if self.loading {
+                    <p class="text-sm text-gray-400">{"Searching…"}</p>
+                }
+                <div class="grid grid-cols-3 gap-1 max-h-64 overflow-y-auto">
+                    {for self.results.iter().map(|gif| {
+                        let url = gif.url.clone();
+                        let select = ctx.props().on_select.reform(move |_: MouseEvent| url.clone());
+                        html! {
+                            <img key={gif.url.clone()} onclick={select} src={gif.preview.clone()} alt="gif result" class="w-full h-20 object-cover rounded cursor-pointer hover:opacity-75" />
+                        }
+                    })}
+                </div>
+                <p class="text-right text-xs text-gray-400 mt-1">{"Powered by Tenor"}</p>
+            </div>
+        }
+    }
+}
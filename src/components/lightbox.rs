@@ -0,0 +1,40 @@
+use gloo::events::EventListener;
+use wasm_bindgen::JsCast;
+use web_sys::KeyboardEvent;
+use yew::prelude::*;
+
+#[derive(Properties, PartialEq)]
+pub struct LightboxProps {
+    pub url: String,
+    pub on_close: Callback<()>,
+}
+
+/// Full-size view of an image over a dark backdrop. Closes on Escape or a
+/// click anywhere.
+#[function_component(Lightbox)]
+pub fn lightbox(props: &LightboxProps) -> Html {
+    {
+        let on_close = props.on_close.clone();
+        use_effect_with_deps(
+            move |_| {
+                let listener = EventListener::new(&gloo::utils::window(), "keydown", move |e| {
+                    let escape = e
+                        .dyn_ref::<KeyboardEvent>()
+                        .is_some_and(|e| e.key() == "Escape");
+                    if escape {
+                        on_close.emit(());
+                    }
+                });
+                move || drop(listener)
+            },
+            (),
+        );
+    }
+    let close = props.on_close.reform(|_: MouseEvent| ());
+
+    html! {
+        <div onclick={close} role="dialog" aria-modal="true" class="fixed inset-0 z-50 flex items-center justify-center bg-black bg-opacity-80 p-8 cursor-zoom-out">
+            <img src={props.url.clone()} alt="full-size image" class="max-w-full max-h-full rounded shadow-lg" />
+        </div>
+    }
+}
@@ -0,0 +1,395 @@
+use yew::prelude::*;
+
+use crate::services::clipboard::copy_to_clipboard;
+
+const FENCE: &str = "```";
+const QUOTE: &str = ">";
+const BOLD: &str = "**";
+const ITALIC: &str = "*";
+
+/// A top-level chunk of a message: either `> ` quoted lines or regular text.
+#[derive(Debug, PartialEq)]
+pub enum Block<'a> {
+    Quote(String),
+    Body(&'a str),
+}
+
+/// Groups consecutive lines starting with `>` into a single quote block.
+/// Lines inside code fences are never treated as quotes.
+pub fn parse_blocks(text: &str) -> Vec<Block<'_>> {
+    let mut blocks = Vec::new();
+    let mut quote: Option<String> = None;
+    let mut body_start = 0;
+    let mut offset = 0;
+    let mut fence = None;
+    for line in text.split_inclusive('\n') {
+        let quoted = fence.is_none().then(|| line.strip_prefix(QUOTE)).flatten();
+        track_fences(line, &mut fence);
+        match quoted {
+            Some(rest) => {
+                if quote.is_none() && body_start < offset {
+                    blocks.push(Block::Body(&text[body_start..offset]));
+                }
+                let rest = rest.strip_prefix(' ').unwrap_or(rest);
+                quote.get_or_insert_with(String::new).push_str(rest);
+            }
+            None => {
+                if let Some(q) = quote.take() {
+                    blocks.push(Block::Quote(q.trim_end_matches('\n').to_string()));
+                    body_start = offset;
+                }
+            }
+        }
+        offset += line.len();
+    }
+    match quote {
+        Some(q) => blocks.push(Block::Quote(q.trim_end_matches('\n').to_string())),
+        None if body_start < text.len() => blocks.push(Block::Body(&text[body_start..])),
+        None => {}
+    }
+    blocks
+}
+
+/// Updates the `open` fence across `line`. As in [`parse_segments`], a fence
+/// is closed only by a run of as many backticks as opened it.
+fn track_fences<'a>(mut line: &'a str, open: &mut Option<&'a str>) {
+    loop {
+        match *open {
+            None => {
+                let start = match line.find(FENCE) {
+                    Some(start) => start,
+                    None => return,
+                };
+                let len = line[start..].len() - line[start..].trim_start_matches('`').len();
+                *open = Some(&line[start..start + len]);
+                line = &line[start + len..];
+            }
+            Some(fence) => {
+                let end = match line.find(fence) {
+                    Some(end) => end,
+                    None => return,
+                };
+                *open = None;
+                line = &line[end + fence.len()..];
+            }
+        }
+    }
+}
+
+/// Prefixes every line of `text` with `> ` so it renders as a quote.
+pub fn quote_text(text: &str) -> String {
+    text.lines().map(|line| format!("> {}\n", line)).collect()
+}
+
+/// A run of message text with uniform formatting.
+#[derive(Debug, PartialEq)]
+pub enum Segment<'a> {
+    Text(&'a str),
+    Bold(&'a str),
+    Italic(&'a str),
+    InlineCode(&'a str),
+    CodeBlock {
+        lang: Option<&'a str>,
+        code: &'a str,
+    },
+}
+
+/// Splits message text into plain text, `**bold**`, `*italic*`,
+/// `` `inline` `` code and fenced ```` ``` ```` code blocks. A block ends at
+/// the same number of backticks that opened it, so longer fences can hold
+/// shorter ones. Unterminated markers are kept as plain text.
+pub fn parse_segments(text: &str) -> Vec<Segment<'_>> {
+    let mut segments = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find(FENCE) {
+        let fence_len = rest[start..].len() - rest[start..].trim_start_matches('`').len();
+        let fence = &rest[start..start + fence_len];
+        let after = &rest[start + fence_len..];
+        let end = match after.find(fence) {
+            Some(end) => end,
+            None => break,
+        };
+        parse_inline(&rest[..start], &mut segments);
+        segments.push(code_block(&after[..end]));
+        rest = &after[end + fence_len..];
+    }
+    parse_inline(rest, &mut segments);
+    segments
+}
+
+/// A fence whose first line is a single word, e.g. ```` ```rust ````, names
+/// the block's language.
+fn code_block(body: &str) -> Segment<'_> {
+    match body.split_once('\n') {
+        Some((lang, code)) if !lang.is_empty() && !lang.contains(char::is_whitespace) => {
+            Segment::CodeBlock {
+                lang: Some(lang),
+                code,
+            }
+        }
+        Some(("", code)) => Segment::CodeBlock { lang: None, code },
+        _ => Segment::CodeBlock {
+            lang: None,
+            code: body,
+        },
+    }
+}
+
+fn parse_inline<'a>(mut text: &'a str, segments: &mut Vec<Segment<'a>>) {
+    while let Some(start) = text.find('`') {
+        let after = &text[start + 1..];
+        let end = match after.find('`') {
+            Some(end) if end > 0 => end,
+            _ => break,
+        };
+        parse_emphasis(&text[..start], segments);
+        segments.push(Segment::InlineCode(&after[..end]));
+        text = &after[end + 1..];
+    }
+    parse_emphasis(text, segments);
+}
+
+fn parse_emphasis<'a>(mut text: &'a str, segments: &mut Vec<Segment<'a>>) {
+    while let Some(start) = text.find(ITALIC) {
+        let marker = if text[start..].starts_with(BOLD) {
+            BOLD
+        } else {
+            ITALIC
+        };
+        let after = &text[start + marker.len()..];
+        let end = match after.find(marker) {
+            Some(end) if end > 0 => end,
+            _ => break,
+        };
+        if start > 0 {
+            segments.push(Segment::Text(&text[..start]));
+        }
+        let inner = &after[..end];
+        segments.push(if marker == BOLD {
+            Segment::Bold(inner)
+        } else {
+            Segment::Italic(inner)
+        });
+        text = &after[end + marker.len()..];
+    }
+    if !text.is_empty() {
+        segments.push(Segment::Text(text));
+    }
+}
+
+/// Formatting the composer toolbar can apply around a selection.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Bold,
+    Italic,
+    Code,
+}
+
+/// Wraps the selection `start..end` of `text`, given in UTF-16 code units as
+/// reported by the DOM, in the markers for `format`. Multi-line code becomes a
+/// fenced block. Returns the new text and the UTF-16 range of the wrapped
+/// selection, which is empty (between the markers) when nothing was selected.
+pub fn wrap_selection(text: &str, start: u32, end: u32, format: Format) -> (String, u32, u32) {
+    let start_byte = utf16_to_byte(text, start);
+    let end_byte = utf16_to_byte(text, end).max(start_byte);
+    let selected = &text[start_byte..end_byte];
+    let (open, close) = match format {
+        Format::Bold => (BOLD, BOLD),
+        Format::Italic => (ITALIC, ITALIC),
+        Format::Code if selected.contains('\n') => ("```\n", "\n```"),
+        Format::Code => ("`", "`"),
+    };
+    let wrapped = format!(
+        "{}{}{}{}{}",
+        &text[..start_byte],
+        open,
+        selected,
+        close,
+        &text[end_byte..]
+    );
+    let new_start = text[..start_byte].encode_utf16().count() + open.len();
+    let new_end = new_start + selected.encode_utf16().count();
+    (wrapped, new_start as u32, new_end as u32)
+}
+
+/// Wraps the whole of `text` in a fenced code block. Leading whitespace is
+/// kept so indentation survives; trailing blank lines are dropped. The fence
+/// is one backtick longer than any run in `text`, so fences in it stay code.
+pub fn fence_text(text: &str) -> String {
+    let text = text.trim_end();
+    let longest = text.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat((longest + 1).max(FENCE.len()));
+    format!("{}\n{}\n{}", fence, text, fence)
+}
+
+/// Byte index of the UTF-16 `offset` into `text`, clamped to its length.
+pub fn utf16_to_byte(text: &str, offset: u32) -> usize {
+    let mut units = 0;
+    for (i, c) in text.char_indices() {
+        if units >= offset as usize {
+            return i;
+        }
+        units += c.len_utf16();
+    }
+    text.len()
+}
+
+/// Renders message text; content is inserted as text nodes, so it is never
+/// interpreted as HTML.
+pub fn render_text(text: &str) -> Html {
+    html! {
+        <div class="whitespace-pre-wrap">
+            {for parse_blocks(text).into_iter().map(|block| match block {
+                Block::Quote(quote) => html! {
+                    <blockquote class="border-l-4 border-gray-300 pl-2 my-1 text-gray-500 italic">
+                        {for parse_segments(&quote).into_iter().map(render_segment)}
+                    </blockquote>
+                },
+                Block::Body(body) => html! {
+                    {for parse_segments(body).into_iter().map(render_segment)}
+                },
+            })}
+        </div>
+    }
+}
+
+fn render_segment(segment: Segment) -> Html {
+    match segment {
+        Segment::Text(text) => html! { <span>{text}</span> },
+        Segment::Bold(text) => html! { <strong>{text}</strong> },
+        Segment::Italic(text) => html! { <em>{text}</em> },
+        Segment::InlineCode(code) => html! {
+            <code class="font-mono bg-gray-100 rounded px-1">{code}</code>
+        },
+        Segment::CodeBlock { lang, code } => {
+            let copy = {
+                let code = code.to_string();
+                Callback::from(move |_| {
+                    if let Err(e) = copy_to_clipboard(&code) {
+                        log::error!("failed to copy code: {:?}", e);
+                    }
+                })
+            };
+            html! {
+                <div class="relative my-1">
+                    <pre class="bg-gray-100 rounded p-2 pr-12 font-mono text-xs whitespace-pre overflow-x-auto" data-lang={lang.map(str::to_string)}><code>{code}</code></pre>
+                    <button onclick={copy} class="absolute top-1 right-1 text-xs text-gray-500 hover:text-gray-800">{"Copy"}</button>
+                </div>
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        fence_text, parse_blocks, parse_segments, utf16_to_byte, wrap_selection, Block, Format,
+        Segment,
+    };
+
+    #[test]
+    fn fences_with_and_without_language() {
+        assert_eq!(
+            parse_segments("```rust\nfn main() {}\n```"),
+            [Segment::CodeBlock {
+                lang: Some("rust"),
+                code: "fn main() {}\n"
+            }]
+        );
+        assert_eq!(
+            parse_segments("see ```\nplain\n``` done"),
+            [
+                Segment::Text("see "),
+                Segment::CodeBlock {
+                    lang: None,
+                    code: "plain\n"
+                },
+                Segment::Text(" done"),
+            ]
+        );
+        assert_eq!(
+            parse_segments("```one line```"),
+            [Segment::CodeBlock {
+                lang: None,
+                code: "one line"
+            }]
+        );
+    }
+
+    #[test]
+    fn unterminated_markers_stay_text() {
+        assert_eq!(parse_segments("**bold"), [Segment::Text("**bold")]);
+        assert_eq!(parse_segments("`code"), [Segment::Text("`code")]);
+        assert_eq!(parse_segments("```\ncode"), [Segment::Text("```\ncode")]);
+        assert_eq!(
+            parse_segments("*a* and *b"),
+            [Segment::Italic("a"), Segment::Text(" and *b")]
+        );
+    }
+
+    #[test]
+    fn consecutive_quote_lines_merge() {
+        assert_eq!(
+            parse_blocks("hi\n> one\n> two\nreply"),
+            [
+                Block::Body("hi\n"),
+                Block::Quote("one\ntwo".to_string()),
+                Block::Body("reply"),
+            ]
+        );
+        let fenced = "```\n> not a quote\n```";
+        assert_eq!(parse_blocks(fenced), [Block::Body(fenced)]);
+    }
+
+    #[test]
+    fn longer_fences_hold_shorter_ones() {
+        let fenced = "````\n```\n> not a quote\n````";
+        assert_eq!(parse_blocks(fenced), [Block::Body(fenced)]);
+        assert_eq!(
+            parse_segments(fenced),
+            [Segment::CodeBlock {
+                lang: None,
+                code: "```\n> not a quote\n"
+            }]
+        );
+    }
+
+    #[test]
+    fn fence_text_outgrows_backticks_in_text() {
+        assert_eq!(fence_text("let x = 1;\n\n"), "```\nlet x = 1;\n```");
+        let fenced = fence_text("a ``` b `c`");
+        assert_eq!(fenced, "````\na ``` b `c`\n````");
+        assert_eq!(
+            parse_segments(&fenced),
+            [Segment::CodeBlock {
+                lang: None,
+                code: "a ``` b `c`\n"
+            }]
+        );
+    }
+
+    #[test]
+    fn wrap_selection_counts_utf16_units() {
+        // The emoji is two UTF-16 units but four bytes.
+        assert_eq!(
+            wrap_selection("😀 hi", 3, 5, Format::Bold),
+            ("😀 **hi**".to_string(), 5, 7)
+        );
+        assert_eq!(
+            wrap_selection("😀", 2, 2, Format::Italic),
+            ("😀**".to_string(), 3, 3)
+        );
+        assert_eq!(
+            wrap_selection("a\nb", 0, 3, Format::Code),
+            ("```\na\nb\n```".to_string(), 4, 7)
+        );
+    }
+
+    #[test]
+    fn utf16_offsets_map_to_char_boundaries() {
+        assert_eq!(utf16_to_byte("é😀x", 3), 6);
+        // Inside a surrogate pair rounds up to the next character.
+        assert_eq!(utf16_to_byte("😀x", 1), 4);
+        assert_eq!(utf16_to_byte("ab", 10), 2);
+    }
+}
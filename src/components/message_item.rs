@@ -0,0 +1,206 @@
+use yew::prelude::*;
+
+use crate::components::card::render_card;
+use crate::components::chat::{format_full_timestamp, MessageData};
+use crate::components::markup::render_text;
+use crate::components::poll::{render_poll, PollTally};
+use crate::components::video::{find_video, VideoPreview};
+use crate::services::preferences::TimeFormat;
+
+/// Messages with more lines than this are collapsed until expanded.
+const COLLAPSE_LINES: usize = 12;
+/// Long single paragraphs wrap onto many lines too; roughly 12 lines' worth.
+const COLLAPSE_CHARS: usize = 1_000;
+
+#[derive(Properties, PartialEq)]
+pub struct MessageItemProps {
+    pub message: MessageData,
+    pub avatar: String,
+    /// Tailwind classes sizing and shaping the avatar.
+    pub avatar_class: Classes,
+    /// Opacity class fading out old messages, if any.
+    pub fade_class: Classes,
+    /// Whether to offer pinning; messages with client-generated ids never
+    /// offer it regardless.
+    pub can_pin: bool,
+    pub pinned: bool,
+    /// Whether we reported the message.
+    pub reported: bool,
+    /// Whether a long message is shown in full.
+    pub expanded: bool,
+    /// Whether the message just arrived and should animate in. Only the value
+    /// at mount counts, so re-renders and remounts don't replay it.
+    pub animate: bool,
+    /// Votes so far, if the message is a poll.
+    pub poll: PollTally,
+    /// Emits the message id and the option index voted for.
+    pub on_vote: Callback<(String, usize)>,
+    /// Number of replies in the message's thread.
+    pub replies: usize,
+    /// Emits the message id.
+    pub on_open_thread: Callback<String>,
+    /// When each earlier version in `message.edit_history` was written,
+    /// formatted for display.
+    pub edit_times: Vec<String>,
+    /// How the send time shown on hover is formatted.
+    pub time_format: TimeFormat,
+    pub locale: String,
+    /// Emits the message text.
+    pub on_quote: Callback<String>,
+    /// Emits the message id.
+    pub on_toggle_pin: Callback<String>,
+    /// Emits the image URL.
+    pub on_open_image: Callback<String>,
+    /// Emits the message id and the viewport coordinates of a right-click.
+    pub on_context_menu: Callback<(String, i32, i32)>,
+    /// Emits the message id.
+    pub on_toggle_expanded: Callback<String>,
+}
+
+/// A single message bubble. Callbacks should be created once by the parent so
+/// that unchanged messages compare equal and skip re-rendering.
+#[function_component(MessageItem)]
+pub fn message_item(props: &MessageItemProps) -> Html {
+    let message = &props.message;
+    let animate = *use_state(|| props.animate);
+    let show_history = use_state(|| false);
+    let quote = {
+        let text = message.message.clone();
+        props.on_quote.reform(move |_: MouseEvent| text.clone())
+    };
+    let pin = {
+        let id = message.id().to_string();
+        props.on_toggle_pin.reform(move |_: MouseEvent| id.clone())
+    };
+
+    let toggle_expanded = {
+        let id = message.id().to_string();
+        props
+            .on_toggle_expanded
+            .reform(move |_: MouseEvent| id.clone())
+    };
+    // Polls are identified by the server id.
+    let vote = (!message.has_local_id()).then(|| {
+        let id = message.id().to_string();
+        props.on_vote.reform(move |option| (id.clone(), option))
+    });
+    let poll = message
+        .metadata
+        .as_ref()
+        .and_then(|metadata| render_poll(metadata, &props.poll, vote));
+
+    let open_thread = {
+        let id = message.id().to_string();
+        props.on_open_thread.reform(move |_: MouseEvent| id.clone())
+    };
+
+    let toggle_history = {
+        let show_history = show_history.clone();
+        Callback::from(move |_: MouseEvent| show_history.set(!*show_history))
+    };
+
+    let sent = message
+        .timestamp()
+        .map(|t| format_full_timestamp(t, props.time_format, &props.locale));
+
+    let collapsible = is_long(&message.message);
+    let collapsed = collapsible && !props.expanded;
+
+    let context_menu = {
+        let id = message.id().to_string();
+        let on_context_menu = props.on_context_menu.clone();
+        Callback::from(move |e: MouseEvent| {
+            e.prevent_default();
+            on_context_menu.emit((id.clone(), e.client_x(), e.client_y()));
+        })
+    };
+
+    html! {
+        <div id={message_dom_id(message.id())} oncontextmenu={context_menu} class={classes!("flex", "items-end", "mb-4", "rounded-lg", props.fade_class.clone(), animate.then_some("animate-message-in motion-reduce:animate-none"))}>
+            <img class={classes!("mr-3", "flex-shrink-0", "select-none", props.avatar_class.clone())} src={props.avatar.clone()} alt={format!("{}'s avatar", message.from)} />
+            <div title={sent} class={classes!("flex", "flex-col", "min-w-0", "rounded-lg", "p-3", if message.bot { "bg-gray-100 border border-gray-200" } else { "bg-white shadow" }, message.ephemeral.then_some("border border-dashed border-gray-400"))}>
+                <div class="flex items-center justify-between">
+                    <span class="text-sm font-medium select-text">
+                        {&message.from}
+                        if message.bot {
+                            <span class="ml-2 px-1 rounded bg-gray-300 text-gray-600 text-[10px] uppercase tracking-wide select-none">{"Bot"}</span>
+                        }
+                    </span>
+                    <div class="relative ml-4 select-none">
+                        if message.edited() {
+                            <button onclick={toggle_history} disabled={message.edit_history.is_empty()} aria-expanded={show_history.to_string()} title={(!message.edit_history.is_empty()).then_some("Show earlier versions")} class="mr-2 text-xs italic text-gray-400 enabled:hover:text-blue-600">{"edited"}</button>
+                        }
+                        if *show_history && !message.edit_history.is_empty() {
+                            <div class="absolute right-0 top-full z-20 mt-1 w-64 max-h-60 overflow-auto rounded-lg bg-white shadow-lg p-3 text-left">
+                                <p class="mb-2 text-xs font-bold text-gray-600">{"Earlier versions"}</p>
+                                <ol class="text-xs">
+                                    {for message.edit_history.iter().zip(&props.edit_times).rev().map(|((text, _), time)| html! {
+                                        <li class="mb-2">
+                                            <span class="block text-gray-400">{time}</span>
+                                            <span class="block text-gray-600 whitespace-pre-wrap break-words select-text">{text}</span>
+                                        </li>
+                                    })}
+                                </ol>
+                            </div>
+                        }
+                        if message.ephemeral {
+                            <span class="mr-2 text-xs italic text-gray-400" title="Not kept in history">{"Disappears on reload"}</span>
+                        }
+                        if props.reported {
+                            <span class="mr-2 text-xs text-red-500">{"Reported"}</span>
+                        }
+                        <button onclick={quote} class="text-xs text-gray-400 hover:text-blue-600">{"Quote"}</button>
+                        if props.can_pin && !message.has_local_id() {
+                            <button onclick={pin} class="ml-2 text-xs text-gray-400 hover:text-blue-600">{if props.pinned { "Unpin" } else { "Pin" }}</button>
+                        }
+                    </div>
+                </div>
+                if let Some(original) = &message.forwarded_from {
+                    <span class="text-[10px] italic text-gray-400 select-none">{format!("Forwarded from {}", original)}</span>
+                }
+                <div class={classes!("relative", if message.bot { "text-gray-500" } else { "text-gray-600" }, "text-xs", "select-text", "break-words", collapsed.then_some("max-h-48 overflow-hidden"))}>
+                    {if let Some(poll) = poll {
+                        poll
+                    } else if let Some(card) = message.metadata.as_ref().and_then(render_card) {
+                        card
+                    } else if message.message.ends_with(".gif") {
+                        let url = message.message.clone();
+                        let open = props.on_open_image.reform(move |_: MouseEvent| url.clone());
+                        html! { <img onclick={open} src={message.message.clone()} alt="gif image" class="cursor-zoom-in" /> }
+                    } else {
+                        html! {
+                            <>
+                                {render_text(&message.message)}
+                                if let Some(video) = find_video(&message.message) {
+                                    <VideoPreview {video} />
+                                }
+                            </>
+                        }
+                    }}
+                    if collapsed {
+                        <div class="absolute bottom-0 inset-x-0 h-12 bg-gradient-to-t from-white pointer-events-none"></div>
+                    }
+                </div>
+                if collapsible {
+                    <button onclick={toggle_expanded} class="self-start mt-1 text-xs text-blue-600 hover:underline select-none">
+                        {if props.expanded { "Show less" } else { "Show more" }}
+                    </button>
+                }
+                if props.replies > 0 {
+                    <button onclick={open_thread} class="self-start mt-1 text-xs font-medium text-blue-600 hover:underline select-none">
+                        {if props.replies == 1 { "1 reply".to_string() } else { format!("{} replies", props.replies) }}
+                    </button>
+                }
+            </div>
+        </div>
+    }
+}
+
+fn is_long(text: &str) -> bool {
+    text.lines().count() > COLLAPSE_LINES || text.chars().count() > COLLAPSE_CHARS
+}
+
+/// DOM id of the element rendering the message with the given id.
+pub fn message_dom_id(id: &str) -> String {
+    format!("msg-{}", id)
+}
@@ -1,2 +1,17 @@
+pub mod announcer;
+pub mod attach_menu;
+pub mod card;
 pub mod chat;
+pub mod context_menu;
+pub mod gif_picker;
+pub mod lightbox;
 pub mod login;
+pub mod markup;
+pub mod message_item;
+pub mod modal;
+pub mod poll;
+pub mod report;
+pub mod settings;
+pub mod shortcuts;
+pub mod toast;
+pub mod video;
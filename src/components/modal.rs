@@ -0,0 +1,30 @@
+use yew::prelude::*;
+
+#[derive(Properties, PartialEq)]
+pub struct ModalProps {
+    pub title: String,
+    #[prop_or_default]
+    pub children: Children,
+    /// Called when the backdrop is clicked; the modal can't be dismissed that
+    /// way when unset.
+    #[prop_or_default]
+    pub on_close: Option<Callback<()>>,
+}
+
+#[function_component(Modal)]
+pub fn modal(props: &ModalProps) -> Html {
+    let onclick_backdrop = props
+        .on_close
+        .clone()
+        .map(|on_close| Callback::from(move |_: MouseEvent| on_close.emit(())));
+
+    html! {
+        <div class="fixed inset-0 z-50 flex items-center justify-center">
+            <div class="absolute inset-0 bg-black bg-opacity-50" onclick={onclick_backdrop}></div>
+            <div role="dialog" aria-modal="true" class="relative bg-white rounded-lg shadow-lg p-6 w-96 max-w-full max-h-[90vh] overflow-y-auto">
+                <h2 class="text-lg font-bold mb-4">{&props.title}</h2>
+                {for props.children.iter()}
+            </div>
+        </div>
+    }
+}
@@ -0,0 +1,130 @@
+use serde_json::Value;
+use web_sys::HtmlInputElement;
+use yew::prelude::*;
+
+use crate::components::modal::Modal;
+
+/// Most options a poll may offer.
+pub const MAX_OPTIONS: usize = 10;
+
+/// Votes cast on a poll so far.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PollTally {
+    /// Votes per option index; options past the end have none.
+    pub counts: Vec<usize>,
+    /// The option we voted for, if any.
+    pub mine: Option<usize>,
+}
+
+/// Renders poll metadata, `{"type": "poll", "title": "…", "options": ["…"]}`,
+/// with a bar per option filled in proportion to its votes, or `None` for
+/// other metadata. Options are buttons emitting their index when `on_vote` is
+/// given.
+pub fn render_poll(
+    value: &Value,
+    tally: &PollTally,
+    on_vote: Option<Callback<usize>>,
+) -> Option<Html> {
+    if value.get("type")?.as_str()? != "poll" {
+        return None;
+    }
+    let title = value.get("title")?.as_str()?;
+    let options: Vec<&str> = value
+        .get("options")?
+        .as_array()?
+        .iter()
+        .filter_map(Value::as_str)
+        .collect();
+    let total: usize = tally.counts.iter().take(options.len()).sum();
+    Some(html! {
+        <div class="border-l-4 border-green-500 pl-3 my-1">
+            <p class="font-bold text-gray-800 text-sm mb-2">{title}</p>
+            {for options.iter().enumerate().map(|(i, option)| {
+                let count = tally.counts.get(i).copied().unwrap_or(0);
+                let percent = (count * 100).checked_div(total).unwrap_or(0);
+                let mine = tally.mine == Some(i);
+                let onclick = on_vote.as_ref().map(|on_vote| on_vote.reform(move |_: MouseEvent| i));
+                html! {
+                    <button {onclick} disabled={on_vote.is_none()} aria-pressed={mine.to_string()} class={classes!("relative", "block", "w-full", "mb-1", "rounded", "border", "overflow-hidden", "text-left", if mine { "border-green-600" } else { "border-gray-300 hover:border-gray-400" })}>
+                        <span class="absolute inset-y-0 left-0 bg-green-100 transition-all motion-reduce:transition-none" style={format!("width: {}%", percent)}></span>
+                        <span class="relative flex justify-between px-2 py-1">
+                            <span>{if mine { format!("✓ {}", option) } else { option.to_string() }}</span>
+                            <span class="ml-2 text-gray-500">{count}</span>
+                        </span>
+                    </button>
+                }
+            })}
+            <p class="text-gray-400">{if total == 1 { "1 vote".to_string() } else { format!("{} votes", total) }}</p>
+        </div>
+    })
+}
+
+#[derive(Properties, PartialEq)]
+pub struct PollDialogProps {
+    /// Emits the question and the non-empty options.
+    pub on_submit: Callback<(String, Vec<String>)>,
+    pub on_close: Callback<()>,
+}
+
+/// Asks for a question and at least two options to start a poll with.
+#[function_component(PollDialog)]
+pub fn poll_dialog(props: &PollDialogProps) -> Html {
+    let question = use_state(String::new);
+    let options = use_state(|| vec![String::new(), String::new()]);
+
+    let on_question = {
+        let question = question.clone();
+        Callback::from(move |e: InputEvent| {
+            question.set(e.target_unchecked_into::<HtmlInputElement>().value())
+        })
+    };
+    let add_option = {
+        let options = options.clone();
+        Callback::from(move |_: MouseEvent| {
+            let mut updated = (*options).clone();
+            updated.push(String::new());
+            options.set(updated);
+        })
+    };
+    let filled: Vec<String> = options
+        .iter()
+        .map(|option| option.trim().to_string())
+        .filter(|option| !option.is_empty())
+        .collect();
+    let ready = !question.trim().is_empty() && filled.len() >= 2;
+    let submit = {
+        let question = question.trim().to_string();
+        props
+            .on_submit
+            .reform(move |_: MouseEvent| (question.clone(), filled.clone()))
+    };
+    let cancel = props.on_close.reform(|_: MouseEvent| ());
+
+    #[allow(clippy::unnecessary_operation)]
+    let view = html! {
+        <Modal title="New poll" on_close={props.on_close.clone()}>
+            <input value={(*question).clone()} oninput={on_question} placeholder="Question" aria-label="Question" class="w-full mb-3 rounded border-2 border-gray-300 p-2 text-sm" />
+            {for options.iter().enumerate().map(|(i, option)| {
+                let oninput = {
+                    let options = options.clone();
+                    Callback::from(move |e: InputEvent| {
+                        let mut updated = (*options).clone();
+                        updated[i] = e.target_unchecked_into::<HtmlInputElement>().value();
+                        options.set(updated);
+                    })
+                };
+                html! {
+                    <input value={option.clone()} {oninput} placeholder={format!("Option {}", i + 1)} aria-label={format!("Option {}", i + 1)} class="w-full mb-2 rounded border-2 border-gray-300 p-1 text-sm" />
+                }
+            })}
+            if options.len() < MAX_OPTIONS {
+                <button onclick={add_option} class="mb-4 text-sm text-blue-600 hover:underline">{"Add option"}</button>
+            }
+            <div class="flex justify-end">
+                <button onclick={cancel} class="px-4 py-2 rounded-lg text-gray-600 hover:bg-gray-100">{"Cancel"}</button>
+                <button onclick={submit} disabled={!ready} class="ml-2 px-4 py-2 rounded-lg bg-blue-600 text-white hover:bg-blue-700 disabled:opacity-50">{"Create poll"}</button>
+            </div>
+        </Modal>
+    };
+    view
+}
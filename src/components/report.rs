@@ -0,0 +1,82 @@
+use serde::Serialize;
+use web_sys::HtmlInputElement;
+use yew::prelude::*;
+
+use crate::components::modal::Modal;
+
+/// Why a message is being reported, sent as the `reason` of a `report` frame.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportReason {
+    Spam,
+    Abuse,
+    Other,
+}
+
+impl ReportReason {
+    pub const ALL: [ReportReason; 3] =
+        [ReportReason::Spam, ReportReason::Abuse, ReportReason::Other];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ReportReason::Spam => "spam",
+            ReportReason::Abuse => "abuse",
+            ReportReason::Other => "other",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ReportReason::Spam => "Spam",
+            ReportReason::Abuse => "Harassment or abuse",
+            ReportReason::Other => "Something else",
+        }
+    }
+}
+
+#[derive(Properties, PartialEq)]
+pub struct ReportDialogProps {
+    pub on_submit: Callback<ReportReason>,
+    pub on_close: Callback<()>,
+}
+
+/// Asks for the reason a message is being reported.
+#[function_component(ReportDialog)]
+pub fn report_dialog(props: &ReportDialogProps) -> Html {
+    let reason = use_state(|| ReportReason::Spam);
+    let submit = {
+        let reason = reason.clone();
+        props.on_submit.reform(move |_: MouseEvent| *reason)
+    };
+    let cancel = props.on_close.reform(|_: MouseEvent| ());
+
+    #[allow(clippy::unnecessary_operation)]
+    let view = html! {
+        <Modal title="Report message" on_close={props.on_close.clone()}>
+            <fieldset class="mb-4 text-sm">
+                <legend class="mb-2 text-gray-600">{"Why are you reporting this message?"}</legend>
+                {for ReportReason::ALL.into_iter().map(|option| {
+                    let onchange = {
+                        let reason = reason.clone();
+                        Callback::from(move |e: Event| {
+                            if e.target_unchecked_into::<HtmlInputElement>().checked() {
+                                reason.set(option);
+                            }
+                        })
+                    };
+                    html! {
+                        <label key={option.as_str()} class="flex items-center py-1">
+                            <input type="radio" name="report-reason" value={option.as_str()} checked={option == *reason} {onchange} class="mr-2" />
+                            {option.label()}
+                        </label>
+                    }
+                })}
+            </fieldset>
+            <div class="flex justify-end">
+                <button onclick={cancel} class="px-4 py-2 rounded-lg text-gray-600 hover:bg-gray-100">{"Cancel"}</button>
+                <button onclick={submit} class="ml-2 px-4 py-2 rounded-lg bg-red-600 text-white hover:bg-red-700">{"Report"}</button>
+            </div>
+        </Modal>
+    };
+    view
+}
@@ -0,0 +1,321 @@
+use std::str::FromStr;
+
+use wasm_bindgen_futures::spawn_local;
+use web_sys::{HtmlInputElement, HtmlSelectElement, HtmlTextAreaElement};
+use yew::prelude::*;
+
+use crate::components::modal::Modal;
+use crate::services::avatar::{avatar_url, AVATAR_SETS};
+use crate::services::preferences::{
+    AvatarShape, AvatarSize, Preferences, ScrollOnLoad, SendKey, Template, TimeFormat,
+    MAX_IDLE_TIMEOUT_MINS,
+};
+use crate::services::sound;
+use crate::services::websocket::ReconnectStrategy;
+
+#[derive(Properties, PartialEq)]
+pub struct SettingsProps {
+    pub preferences: Preferences,
+    /// Seed for the avatar previews.
+    pub user_id: String,
+    pub on_change: Callback<Preferences>,
+    pub on_close: Callback<()>,
+}
+
+/// Builds an `onchange` handler that parses the input's or select's value and
+/// emits a copy of the preferences with `apply` run on it. Unparsable values
+/// are ignored.
+fn on_value<T: FromStr + 'static>(
+    props: &SettingsProps,
+    apply: fn(&mut Preferences, T),
+) -> Callback<Event> {
+    let preferences = props.preferences.clone();
+    let on_change = props.on_change.clone();
+    Callback::from(move |e: Event| {
+        let value = match e.target_dyn_into::<HtmlSelectElement>() {
+            Some(select) => select.value(),
+            None => e.target_unchecked_into::<HtmlInputElement>().value(),
+        };
+        if let Ok(value) = value.parse() {
+            let mut preferences = preferences.clone();
+            apply(&mut preferences, value);
+            on_change.emit(preferences);
+        }
+    })
+}
+
+/// Like [`on_value`] for checkboxes.
+fn on_checked(props: &SettingsProps, apply: fn(&mut Preferences, bool)) -> Callback<Event> {
+    let preferences = props.preferences.clone();
+    let on_change = props.on_change.clone();
+    Callback::from(move |e: Event| {
+        let input: HtmlInputElement = e.target_unchecked_into();
+        let mut preferences = preferences.clone();
+        apply(&mut preferences, input.checked());
+        on_change.emit(preferences);
+    })
+}
+
+/// Builds an `onchange` handler for a field of the template at `index`.
+fn on_template(
+    props: &SettingsProps,
+    index: usize,
+    apply: fn(&mut Template, String),
+) -> Callback<Event> {
+    let preferences = props.preferences.clone();
+    let on_change = props.on_change.clone();
+    Callback::from(move |e: Event| {
+        let value = match e.target_dyn_into::<HtmlTextAreaElement>() {
+            Some(textarea) => textarea.value(),
+            None => e.target_unchecked_into::<HtmlInputElement>().value(),
+        };
+        let mut preferences = preferences.clone();
+        if let Some(template) = preferences.templates.get_mut(index) {
+            apply(template, value);
+            on_change.emit(preferences);
+        }
+    })
+}
+
+fn view_templates(props: &SettingsProps) -> Html {
+    let add = {
+        let mut preferences = props.preferences.clone();
+        preferences.templates.push(Template {
+            name: format!("template-{}", preferences.templates.len() + 1),
+            text: String::new(),
+        });
+        props
+            .on_change
+            .reform(move |_: MouseEvent| preferences.clone())
+    };
+    html! {
+        <>
+            <p class="mb-2 text-sm">{"Templates"}<span class="text-gray-500">{" – insert with /template <name>"}</span></p>
+            {for props.preferences.templates.iter().enumerate().map(|(i, template)| {
+                // Command arguments are split on whitespace.
+                let on_name = on_template(props, i, |t, name| t.name = name.split_whitespace().collect::<Vec<_>>().join("-"));
+                let on_text = on_template(props, i, |t, text| t.text = text);
+                let delete = {
+                    let mut preferences = props.preferences.clone();
+                    preferences.templates.remove(i);
+                    props.on_change.reform(move |_: MouseEvent| preferences.clone())
+                };
+                html! {
+                    <div class="mb-2 text-sm">
+                        <div class="flex items-center mb-1">
+                            <input value={template.name.clone()} onchange={on_name} aria-label="Template name" class="flex-grow rounded border-2 border-gray-300 p-1" />
+                            <button onclick={delete} class="ml-2 text-red-600 hover:underline">{"Delete"}</button>
+                        </div>
+                        <textarea value={template.text.clone()} onchange={on_text} rows="2" aria-label="Template text" class="w-full rounded border-2 border-gray-300 p-1"></textarea>
+                    </div>
+                }
+            })}
+            <button onclick={add} class="mb-4 text-sm text-blue-600 hover:underline">{"Add template"}</button>
+        </>
+    }
+}
+
+#[function_component(Settings)]
+pub fn settings(props: &SettingsProps) -> Html {
+    let on_idle_timeout = on_value(props, |p, mins: u32| {
+        p.idle_timeout_mins = mins.min(MAX_IDLE_TIMEOUT_MINS)
+    });
+    let on_time_format = on_value(props, |p, format| p.time_format = format);
+    let on_send_key = on_value(props, |p, key| p.send_key = key);
+    let on_reconnect = on_value(props, |p, strategy| p.reconnect = strategy);
+    let on_scroll_on_load = on_value(props, |p, scroll| p.scroll_on_load = scroll);
+    let on_backoff_base = on_value(props, |p, ms| {
+        if let ReconnectStrategy::Backoff { base_ms, .. } = &mut p.reconnect {
+            *base_ms = ms;
+        }
+    });
+    let on_backoff_cap = on_value(props, |p, ms| {
+        if let ReconnectStrategy::Backoff { cap_ms, .. } = &mut p.reconnect {
+            *cap_ms = ms;
+        }
+    });
+    let on_group_window = on_value(props, |p, secs| p.group_window_secs = secs);
+    let on_avatar_size = on_value(props, |p, size| p.avatar_size = size);
+    let on_avatar_shape = on_value(props, |p, shape| p.avatar_shape = shape);
+    let avatar_sets = AVATAR_SETS.map(|set| {
+        let mut preferences = props.preferences.clone();
+        preferences.avatar_set = set;
+        let select = props.on_change.reform(move |_: MouseEvent| preferences.clone());
+        let class = if set == props.preferences.avatar_set {
+            "rounded-lg border-2 border-blue-600 bg-blue-50"
+        } else {
+            "rounded-lg border-2 border-transparent hover:bg-gray-100"
+        };
+        html! {
+            <button key={set} onclick={select} title={format!("Set {}", set)} {class}>
+                <img class="w-12 h-12" src={avatar_url(&props.user_id, set)} alt={format!("Avatar set {}", set)} />
+            </button>
+        }
+    });
+    let on_notification_sound = on_checked(props, |p, on| p.notification_sound = on);
+    let on_spellcheck = on_checked(props, |p, on| p.spellcheck = on);
+    let on_fade_old_messages = on_checked(props, |p, on| p.fade_old_messages = on);
+    let on_do_not_disturb = on_checked(props, |p, on| p.do_not_disturb = on);
+    let on_quiet_hours = on_checked(props, |p, on| p.quiet_hours.enabled = on);
+    let on_quiet_start = on_value(props, |p, time| p.quiet_hours.start = time);
+    let on_quiet_end = on_value(props, |p, time| p.quiet_hours.end = time);
+
+    // Bumped whenever the stored sound changes so the label re-renders.
+    let sound_version = use_state(|| 0u32);
+    let sound_error = use_state(|| None::<String>);
+    let on_sound_file = {
+        let sound_version = sound_version.clone();
+        let sound_error = sound_error.clone();
+        Callback::from(move |e: Event| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            let file = match input.files().and_then(|files| files.get(0)) {
+                Some(file) => gloo::file::File::from(file),
+                None => return,
+            };
+            input.set_value("");
+            let sound_version = sound_version.clone();
+            let sound_error = sound_error.clone();
+            spawn_local(async move {
+                match sound::set_custom_sound(file).await {
+                    Ok(()) => {
+                        sound_error.set(None);
+                        sound_version.set(*sound_version + 1);
+                    }
+                    Err(e) => sound_error.set(Some(e)),
+                }
+            });
+        })
+    };
+    let reset_sound = {
+        let sound_version = sound_version.clone();
+        Callback::from(move |_| {
+            sound::clear_custom_sound();
+            sound_version.set(*sound_version + 1);
+        })
+    };
+    let close = {
+        let on_close = props.on_close.clone();
+        Callback::from(move |_| on_close.emit(()))
+    };
+
+    #[allow(clippy::unnecessary_operation)]
+    let view = html! {
+        <Modal title="Settings" on_close={props.on_close.clone()}>
+            <label class="flex items-center justify-between mb-4 text-sm">
+                <span>{"Log out after inactivity (minutes, 0 = never)"}</span>
+                <input type="number" min="0" max={MAX_IDLE_TIMEOUT_MINS.to_string()} value={props.preferences.idle_timeout_mins.to_string()} onchange={on_idle_timeout} class="w-20 rounded border-2 border-gray-300 p-1 ml-2" />
+            </label>
+            <label class="flex items-center justify-between mb-4 text-sm">
+                <span>{"Time format"}</span>
+                <select onchange={on_time_format} class="rounded border-2 border-gray-300 p-1 ml-2">
+                    {for TimeFormat::ALL.into_iter().map(|format| html! {
+                        <option value={format.as_str()} selected={format == props.preferences.time_format}>{format.label()}</option>
+                    })}
+                </select>
+            </label>
+            <label class="flex items-center justify-between mb-4 text-sm">
+                <span>{"Group messages sent within (seconds)"}</span>
+                <input type="number" min="0" value={props.preferences.group_window_secs.to_string()} onchange={on_group_window} class="w-20 rounded border-2 border-gray-300 p-1 ml-2" />
+            </label>
+            <label class="flex items-center justify-between mb-4 text-sm">
+                <span>{"Send messages with"}</span>
+                <select onchange={on_send_key} class="rounded border-2 border-gray-300 p-1 ml-2">
+                    {for SendKey::ALL.into_iter().map(|key| html! {
+                        <option value={key.as_str()} selected={key == props.preferences.send_key}>{key.label()}</option>
+                    })}
+                </select>
+            </label>
+            <label class="flex items-center justify-between mb-4 text-sm">
+                <span>{"After reloading the page"}</span>
+                <select onchange={on_scroll_on_load} class="rounded border-2 border-gray-300 p-1 ml-2">
+                    {for ScrollOnLoad::ALL.into_iter().map(|scroll| html! {
+                        <option value={scroll.as_str()} selected={scroll == props.preferences.scroll_on_load}>{scroll.label()}</option>
+                    })}
+                </select>
+            </label>
+            <label class="flex items-center justify-between mb-4 text-sm">
+                <span>{"Reconnect after losing the connection"}</span>
+                <select onchange={on_reconnect} class="rounded border-2 border-gray-300 p-1 ml-2">
+                    {for ReconnectStrategy::ALL.into_iter().map(|strategy| html! {
+                        <option value={strategy.as_str()} selected={strategy.as_str() == props.preferences.reconnect.as_str()}>{strategy.label()}</option>
+                    })}
+                </select>
+            </label>
+            if let ReconnectStrategy::Backoff { base_ms, cap_ms } = props.preferences.reconnect {
+                <div class="flex items-center justify-between mb-4 text-sm">
+                    <span>{"First and longest wait (ms)"}</span>
+                    <div>
+                        <input type="number" min="0" value={base_ms.to_string()} onchange={on_backoff_base} aria-label="First wait in milliseconds" class="w-20 rounded border-2 border-gray-300 p-1 ml-2" />
+                        <input type="number" min="0" value={cap_ms.to_string()} onchange={on_backoff_cap} aria-label="Longest wait in milliseconds" class="w-20 rounded border-2 border-gray-300 p-1 ml-2" />
+                    </div>
+                </div>
+            }
+            <label class="flex items-center justify-between mb-4 text-sm">
+                <span>{"Avatar size"}</span>
+                <select onchange={on_avatar_size} class="rounded border-2 border-gray-300 p-1 ml-2">
+                    {for AvatarSize::ALL.into_iter().map(|size| html! {
+                        <option value={size.as_str()} selected={size == props.preferences.avatar_size}>{size.label()}</option>
+                    })}
+                </select>
+            </label>
+            <label class="flex items-center justify-between mb-4 text-sm">
+                <span>{"Avatar shape"}</span>
+                <select onchange={on_avatar_shape} class="rounded border-2 border-gray-300 p-1 ml-2">
+                    {for AvatarShape::ALL.into_iter().map(|shape| html! {
+                        <option value={shape.as_str()} selected={shape == props.preferences.avatar_shape}>{shape.label()}</option>
+                    })}
+                </select>
+            </label>
+            <p class="mb-2 text-sm">{"Avatar style"}</p>
+            <div class="flex justify-between mb-4">{for avatar_sets}</div>
+            <label class="flex items-center justify-between mb-4 text-sm">
+                <span>{"Fade out older messages"}</span>
+                <input type="checkbox" checked={props.preferences.fade_old_messages} onchange={on_fade_old_messages} class="ml-2" />
+            </label>
+            <label class="flex items-center justify-between mb-4 text-sm">
+                <span>{"Check spelling while typing"}</span>
+                <input type="checkbox" checked={props.preferences.spellcheck} onchange={on_spellcheck} class="ml-2" />
+            </label>
+            <label class="flex items-center justify-between mb-2 text-sm">
+                <span>{"Play a sound for new messages while in the background"}</span>
+                <input type="checkbox" checked={props.preferences.notification_sound} onchange={on_notification_sound} class="ml-2" />
+            </label>
+            <div class="flex items-center justify-between mb-4 text-sm">
+                <span class="text-gray-500">{if sound::has_custom_sound() { "Custom sound" } else { "Default chime" }}</span>
+                <div class="flex items-center">
+                    <button onclick={Callback::from(|_| sound::play_notification())} class="text-blue-600 hover:underline">{"Test"}</button>
+                    <label class="ml-3 text-blue-600 hover:underline cursor-pointer">
+                        {"Upload…"}
+                        <input type="file" accept="audio/*" onchange={on_sound_file} class="hidden" />
+                    </label>
+                    if sound::has_custom_sound() {
+                        <button onclick={reset_sound} class="ml-3 text-blue-600 hover:underline">{"Use default"}</button>
+                    }
+                </div>
+            </div>
+            if let Some(error) = &*sound_error {
+                <p role="alert" class="mb-4 text-sm text-red-600">{error}</p>
+            }
+            <label class="flex items-center justify-between mb-2 text-sm">
+                <span>{"Do not disturb"}</span>
+                <input type="checkbox" checked={props.preferences.do_not_disturb} onchange={on_do_not_disturb} class="ml-2" />
+            </label>
+            <div class="flex items-center justify-between mb-4 text-sm">
+                <label class="flex items-center">
+                    <input type="checkbox" checked={props.preferences.quiet_hours.enabled} onchange={on_quiet_hours} class="mr-2" />
+                    {"Quiet hours"}
+                </label>
+                <div class="flex items-center">
+                    <input type="time" value={props.preferences.quiet_hours.start.to_string()} onchange={on_quiet_start} disabled={!props.preferences.quiet_hours.enabled} aria-label="Quiet hours start" class="rounded border-2 border-gray-300 p-1 disabled:opacity-50" />
+                    <span class="mx-1">{"–"}</span>
+                    <input type="time" value={props.preferences.quiet_hours.end.to_string()} onchange={on_quiet_end} disabled={!props.preferences.quiet_hours.enabled} aria-label="Quiet hours end" class="rounded border-2 border-gray-300 p-1 disabled:opacity-50" />
+                </div>
+            </div>
+            {view_templates(props)}
+            <div class="flex justify-end">
+                <button onclick={close} class="px-4 py-2 rounded-lg bg-blue-600 text-white hover:bg-blue-700">{"Done"}</button>
+            </div>
+        </Modal>
+    };
+    view
+}
@@ -0,0 +1,64 @@
+use web_sys::{Element, KeyboardEvent};
+use yew::prelude::*;
+
+use crate::components::modal::Modal;
+use crate::services::preferences::SendKey;
+
+/// Every keyboard shortcut besides sending, which depends on [`SendKey`], as
+/// shown in the help overlay. Add new shortcuts here so the overlay stays
+/// complete.
+pub const SHORTCUTS: &[(&str, &str)] = &[
+    ("Alt + ↑ / ↓", "Previous or next room"),
+    (
+        "Alt + Shift + ↑ / ↓",
+        "Previous or next room with unread messages",
+    ),
+    ("Esc", "Close the image viewer"),
+    ("?", "Show this list"),
+];
+
+/// Whether a key press is meant for a text field rather than the app.
+pub fn is_typing_target(e: &KeyboardEvent) -> bool {
+    e.target_dyn_into::<Element>().is_some_and(|element| {
+        matches!(element.tag_name().as_str(), "INPUT" | "TEXTAREA" | "SELECT")
+    })
+}
+
+#[derive(Properties, PartialEq)]
+pub struct ShortcutHelpProps {
+    pub send_key: SendKey,
+    pub on_close: Callback<()>,
+}
+
+#[function_component(ShortcutHelp)]
+pub fn shortcut_help(props: &ShortcutHelpProps) -> Html {
+    let close = props.on_close.reform(|_: MouseEvent| ());
+    let composer: [(&str, &str); 2] = match props.send_key {
+        SendKey::Enter => [
+            ("Enter", "Send the message"),
+            ("Shift + Enter", "Start a new line"),
+        ],
+        SendKey::CtrlEnter => [
+            ("Ctrl + Enter", "Send the message"),
+            ("Enter", "Start a new line"),
+        ],
+    };
+
+    #[allow(clippy::unnecessary_operation)]
+    let view = html! {
+        <Modal title="Keyboard shortcuts" on_close={props.on_close.clone()}>
+            <dl class="mb-4 text-sm">
+                {for composer.iter().chain(SHORTCUTS).map(|(keys, action)| html! {
+                    <div class="flex justify-between py-1">
+                        <dt><kbd class="rounded border border-gray-300 bg-gray-100 px-1 font-mono text-xs">{*keys}</kbd></dt>
+                        <dd class="text-gray-600">{*action}</dd>
+                    </div>
+                })}
+            </dl>
+            <div class="flex justify-end">
+                <button onclick={close} class="px-4 py-2 rounded-lg bg-blue-600 text-white hover:bg-blue-700">{"Close"}</button>
+            </div>
+        </Modal>
+    };
+    view
+}
@@ -0,0 +1,101 @@
+use gloo::timers::callback::Timeout;
+use yew::prelude::*;
+use yew_agent::{Bridge, Bridged};
+
+use crate::services::event_bus::{Event, EventBus};
+use crate::services::toast::{ToastLevel, ToastMessage};
+
+#[derive(Properties, PartialEq)]
+pub struct ToastProps {
+    pub text: String,
+    pub level: ToastLevel,
+    pub on_close: Callback<()>,
+}
+
+#[function_component(Toast)]
+pub fn toast(props: &ToastProps) -> Html {
+    let class = match props.level {
+        ToastLevel::Info => "bg-gray-800 text-white",
+        ToastLevel::Success => "bg-green-600 text-white",
+        ToastLevel::Error => "bg-red-600 text-white",
+    };
+    let role = match props.level {
+        ToastLevel::Error => "alert",
+        ToastLevel::Info | ToastLevel::Success => "status",
+    };
+    let close = props.on_close.reform(|_: MouseEvent| ());
+
+    html! {
+        <div {role} class={classes!("flex", "items-center", "justify-between", "min-w-64", "max-w-sm", "mt-2", "rounded-lg", "shadow-lg", "px-4", "py-2", "text-sm", "pointer-events-auto", class)}>
+            <span>{&props.text}</span>
+            <button onclick={close} title="Dismiss" class="ml-4 opacity-75 hover:opacity-100">{"✕"}</button>
+        </div>
+    }
+}
+
+pub enum Msg {
+    Show(ToastMessage),
+    Dismiss(u64),
+}
+
+/// Stack of toasts in the bottom-right corner. Anything can add one through
+/// the [`EventBus`], e.g. with `show_toast`; each dismisses itself after its
+/// `ttl_ms` or when closed.
+pub struct ToastHub {
+    toasts: Vec<(u64, ToastMessage, Timeout)>,
+    next_id: u64,
+    _bus: Box<dyn Bridge<EventBus>>,
+}
+
+impl Component for ToastHub {
+    type Message = Msg;
+    type Properties = ();
+
+    fn create(ctx: &Context<Self>) -> Self {
+        Self {
+            toasts: vec![],
+            next_id: 0,
+            _bus: EventBus::bridge(ctx.link().batch_callback(|event| match event {
+                Event::Toast(toast) => Some(Msg::Show(toast)),
+                _ => None,
+            })),
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::Show(toast) => {
+                self.next_id += 1;
+                let id = self.next_id;
+                let dismiss = ctx.link().callback(move |_| Msg::Dismiss(id));
+                let timeout = Timeout::new(toast.ttl_ms, move || dismiss.emit(()));
+                self.toasts.push((id, toast, timeout));
+                true
+            }
+            Msg::Dismiss(id) => {
+                let before = self.toasts.len();
+                self.toasts.retain(|(t, _, _)| *t != id);
+                self.toasts.len() != before
+            }
+        }
+    }
+
+    #[allow(clippy::unnecessary_operation)]
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        html! {
+            <div class="fixed bottom-4 right-4 z-50 flex flex-col items-end pointer-events-none">
+                {for self.toasts.iter().map(|(id, toast, _)| {
+                    let id = *id;
+                    html! {
+                        <Toast
+                            key={id}
+                            text={toast.text.clone()}
+                            level={toast.level}
+                            on_close={ctx.link().callback(move |_| Msg::Dismiss(id))}
+                        />
+                    }
+                })}
+            </div>
+        }
+    }
+}
@@ -0,0 +1,227 @@
+use yew::prelude::*;
+
+/// Video hosts whose links get an embedded player.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Provider {
+    YouTube,
+    Vimeo,
+}
+
+/// A link to a video on a known host.
+#[derive(Clone, Debug, PartialEq)]
+pub struct VideoLink {
+    pub provider: Provider,
+    pub id: String,
+    /// The link as written in the message.
+    pub url: String,
+}
+
+impl VideoLink {
+    /// Recognises `youtube.com/watch?v=…`, `youtube.com/shorts/…`,
+    /// `youtu.be/…` and `vimeo.com/<number>` links; anything else is `None`.
+    pub fn parse(url: &str) -> Option<Self> {
+        let rest = url
+            .strip_prefix("https://")
+            .or_else(|| url.strip_prefix("http://"))?;
+        let rest = rest
+            .strip_prefix("www.")
+            .or_else(|| rest.strip_prefix("m."))
+            .unwrap_or(rest);
+        let (host, path) = rest.split_once('/')?;
+        let (provider, id) = match host {
+            "youtube.com" => {
+                let id = match path.strip_prefix("watch?") {
+                    Some(query) => query.split('&').find_map(|pair| pair.strip_prefix("v="))?,
+                    None => path.strip_prefix("shorts/")?,
+                };
+                (Provider::YouTube, id)
+            }
+            "youtu.be" => (Provider::YouTube, path),
+            "vimeo.com" => (Provider::Vimeo, path),
+            _ => return None,
+        };
+        let id = id.split(['?', '&', '#', '/']).next()?;
+        let valid = match provider {
+            Provider::YouTube => {
+                id.len() == 11
+                    && id
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+            }
+            Provider::Vimeo => !id.is_empty() && id.chars().all(|c| c.is_ascii_digit()),
+        };
+        valid.then(|| VideoLink {
+            provider,
+            id: id.to_string(),
+            url: url.to_string(),
+        })
+    }
+
+    /// Player URL, starting playback as soon as it loads since it is only
+    /// loaded on click. Uses the hosts' no-tracking variants.
+    fn embed_url(&self) -> String {
+        match self.provider {
+            Provider::YouTube => format!(
+                "https://www.youtube-nocookie.com/embed/{}?autoplay=1",
+                self.id
+            ),
+            Provider::Vimeo => format!(
+                "https://player.vimeo.com/video/{}?autoplay=1&dnt=1",
+                self.id
+            ),
+        }
+    }
+
+    /// Still image to show before playing; Vimeo only has them through its
+    /// API.
+    fn thumbnail(&self) -> Option<String> {
+        match self.provider {
+            Provider::YouTube => Some(format!("https://i.ytimg.com/vi/{}/hqdefault.jpg", self.id)),
+            Provider::Vimeo => None,
+        }
+    }
+
+    fn host_name(&self) -> &'static str {
+        match self.provider {
+            Provider::YouTube => "YouTube",
+            Provider::Vimeo => "Vimeo",
+        }
+    }
+}
+
+/// First link in `text` to a known video host.
+pub fn find_video(text: &str) -> Option<VideoLink> {
+    text.split_whitespace()
+        .map(|word| word.trim_end_matches(['.', ',', ')', '!', '?']))
+        .find_map(VideoLink::parse)
+}
+
+#[derive(Properties, PartialEq)]
+pub struct VideoPreviewProps {
+    pub video: VideoLink,
+}
+
+/// Thumbnail with a play button that swaps in the host's player when
+/// clicked, so nothing from the host but the thumbnail loads until then.
+#[function_component(VideoPreview)]
+pub fn video_preview(props: &VideoPreviewProps) -> Html {
+    let playing = use_state(|| false);
+    let video = &props.video;
+    let title = format!("{} video", video.host_name());
+    if *playing {
+        return html! {
+            <iframe src={video.embed_url()} {title} allow="autoplay; encrypted-media; picture-in-picture" allowfullscreen={true} class="w-full max-w-sm aspect-video mt-2 rounded border-0"></iframe>
+        };
+    }
+    let play = Callback::from(move |_: MouseEvent| playing.set(true));
+    html! {
+        <div class="max-w-sm mt-2">
+            <button onclick={play} aria-label={format!("Play {}", title)} class="group relative block w-full aspect-video rounded overflow-hidden bg-gray-800">
+                if let Some(thumbnail) = video.thumbnail() {
+                    <img src={thumbnail} alt="" loading="lazy" class="w-full h-full object-cover" />
+                }
+                <span class="absolute inset-0 flex items-center justify-center">
+                    <span class="flex items-center justify-center w-12 h-12 rounded-full bg-black bg-opacity-60 text-white text-xl group-hover:bg-red-600">{"▶"}</span>
+                </span>
+            </button>
+            <a href={video.url.clone()} target="_blank" rel="noopener noreferrer" class="text-blue-600 hover:underline">{format!("Open on {}", video.host_name())}</a>
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_video, Provider, VideoLink};
+
+    const ID: &str = "dQw4w9WgXcQ";
+
+    fn youtube_id(url: &str) -> Option<String> {
+        VideoLink::parse(url)
+            .filter(|link| link.provider == Provider::YouTube)
+            .map(|link| link.id)
+    }
+
+    #[test]
+    fn watch_links_in_either_query_order() {
+        assert_eq!(
+            VideoLink::parse("https://youtube.com/watch?v=dQw4w9WgXcQ&t=42"),
+            Some(VideoLink {
+                provider: Provider::YouTube,
+                id: ID.to_string(),
+                url: "https://youtube.com/watch?v=dQw4w9WgXcQ&t=42".to_string(),
+            })
+        );
+        assert_eq!(
+            youtube_id("https://youtube.com/watch?t=42&v=dQw4w9WgXcQ"),
+            Some(ID.to_string())
+        );
+    }
+
+    #[test]
+    fn shorts_links() {
+        assert_eq!(
+            youtube_id("https://youtube.com/shorts/dQw4w9WgXcQ?feature=share"),
+            Some(ID.to_string())
+        );
+    }
+
+    #[test]
+    fn short_host_links() {
+        assert_eq!(
+            youtube_id("https://youtu.be/dQw4w9WgXcQ?t=1"),
+            Some(ID.to_string())
+        );
+    }
+
+    #[test]
+    fn www_and_mobile_prefixes() {
+        assert_eq!(
+            youtube_id("http://www.youtube.com/watch?v=dQw4w9WgXcQ"),
+            Some(ID.to_string())
+        );
+        assert_eq!(
+            youtube_id("https://m.youtube.com/watch?v=dQw4w9WgXcQ"),
+            Some(ID.to_string())
+        );
+    }
+
+    #[test]
+    fn youtube_ids_are_eleven_characters() {
+        assert_eq!(youtube_id("https://youtu.be/dQw4w9WgXc"), None);
+        assert_eq!(youtube_id("https://youtu.be/dQw4w9WgXcQQ"), None);
+        assert_eq!(youtube_id("https://youtu.be/dQw4w9WgX.Q"), None);
+        assert_eq!(
+            youtube_id("https://youtu.be/a-b_c-d_e-f"),
+            Some("a-b_c-d_e-f".to_string())
+        );
+    }
+
+    #[test]
+    fn vimeo_ids_are_numeric() {
+        let link = VideoLink::parse("https://vimeo.com/76979871").unwrap();
+        assert_eq!(link.provider, Provider::Vimeo);
+        assert_eq!(link.id, "76979871");
+        assert_eq!(VideoLink::parse("https://vimeo.com/channels/x"), None);
+    }
+
+    #[test]
+    fn trailing_punctuation_is_dropped() {
+        let link = find_video("watch this https://youtu.be/dQw4w9WgXcQ!").unwrap();
+        assert_eq!(link.id, ID);
+        assert_eq!(link.url, "https://youtu.be/dQw4w9WgXcQ");
+        let link = find_video("see https://vimeo.com/76979871.").unwrap();
+        assert_eq!(link.id, "76979871");
+    }
+
+    #[test]
+    fn look_alike_hosts_are_rejected() {
+        assert_eq!(
+            VideoLink::parse("https://youtube.com.evil.com/watch?v=dQw4w9WgXcQ"),
+            None
+        );
+        assert_eq!(
+            find_video("no video https://example.com/watch?v=dQw4w9WgXcQ"),
+            None
+        );
+    }
+}
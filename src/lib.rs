@@ -11,8 +11,10 @@ use yew::functional::*;
 use yew::prelude::*;
 use yew_router::prelude::*;
 
+use components::announcer::ConnectionAnnouncer;
 use components::chat::Chat;
 use components::login::Login;
+use components::toast::ToastHub;
 
 // When the `wee_alloc` feature is enabled, this uses `wee_alloc` as the global
 // allocator.
@@ -44,32 +46,52 @@ pub struct UserInner {
 fn main() -> Html {
     let ctx = use_state(|| {
         Rc::new(UserInner {
-            username: RefCell::new("initial".into()),
+            username: RefCell::new(String::new()),
         })
     });
 
-    html! {
+    // `html!` type-checks component props with statements clippy flags.
+    #[allow(clippy::let_unit_value, clippy::unnecessary_operation)]
+    let view = html! {
         <ContextProvider<User> context={(*ctx).clone()}>
             <BrowserRouter>
                 <div class="flex w-screen h-screen">
                     <Switch<Route> render={Switch::render(switch)}/>
                 </div>
+                <ToastHub />
+                <ConnectionAnnouncer />
             </BrowserRouter>
         </ContextProvider<User>>
-    }
+    };
+    view
 }
 
+#[allow(clippy::let_unit_value)]
 fn switch(selected_route: &Route) -> Html {
     match selected_route {
         Route::Login => html! {<Login />},
-        Route::Chat => html! {<Chat/>},
+        Route::Chat => html! {<RequireLogin />},
         Route::NotFound => html! {<h1>{"404 baby"}</h1>},
     }
 }
 
+/// Renders the chat for a logged-in user and sends everyone else to the login
+/// screen, e.g. when `/chat` is opened directly.
+#[function_component(RequireLogin)]
+fn require_login() -> Html {
+    let logged_in = use_context::<User>().is_some_and(|user| !user.username.borrow().is_empty());
+    if logged_in {
+        #[allow(clippy::let_unit_value)]
+        let chat = html! {<Chat/>};
+        chat
+    } else {
+        html! {<Redirect<Route> to={Route::Login}/>}
+    }
+}
+
 #[wasm_bindgen]
 pub fn run_app() -> Result<(), JsValue> {
-    wasm_logger::init(wasm_logger::Config::default());
+    services::logging::init();
     yew::start_app::<Main>();
     Ok(())
 }
@@ -0,0 +1,15 @@
+use gloo::storage::{LocalStorage, Storage};
+
+/// `localStorage` key holding the bearer token for authenticated servers.
+/// Whatever signs the user in stores it there; servers without accounts
+/// never need one.
+const TOKEN_KEY: &str = "yewchat.auth-token";
+
+pub fn load_token() -> Option<String> {
+    LocalStorage::get(TOKEN_KEY).ok()
+}
+
+/// Forgets the token, e.g. once the server has rejected it.
+pub fn clear_token() {
+    LocalStorage::delete(TOKEN_KEY);
+}
@@ -0,0 +1,13 @@
+use std::ops::RangeInclusive;
+
+/// Robohash art styles: robots, monsters, robot heads, cats and humans.
+pub const AVATAR_SETS: RangeInclusive<u8> = 1..=5;
+
+/// Set used for users who haven't picked one.
+pub const DEFAULT_AVATAR_SET: u8 = 4;
+
+/// Avatar for `user_id` drawn from the given Robohash set. The same id gives
+/// the same picture within a set.
+pub fn avatar_url(user_id: &str, set: u8) -> String {
+    format!("https://robohash.org/{}.png?set=set{}", user_id, set)
+}
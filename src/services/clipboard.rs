@@ -0,0 +1,24 @@
+use js_sys::{Function, Reflect};
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::{spawn_local, JsFuture};
+
+/// Writes `text` to the system clipboard.
+///
+/// `navigator.clipboard` is still behind `web_sys_unstable_apis`, so it is
+/// looked up dynamically instead of through the generated bindings.
+pub fn copy_to_clipboard(text: &str) -> Result<(), JsValue> {
+    let window = web_sys::window().ok_or_else(|| JsValue::from_str("no window"))?;
+    let clipboard = Reflect::get(&window.navigator(), &JsValue::from_str("clipboard"))?;
+    let write_text: Function =
+        Reflect::get(&clipboard, &JsValue::from_str("writeText"))?.dyn_into()?;
+    let promise: js_sys::Promise = write_text
+        .call1(&clipboard, &JsValue::from_str(text))?
+        .dyn_into()?;
+
+    spawn_local(async move {
+        if let Err(e) = JsFuture::from(promise).await {
+            log::error!("clipboard: {:?}", e);
+        }
+    });
+    Ok(())
+}
@@ -0,0 +1,133 @@
+use js_sys::{Array, Function, Promise, Reflect, Uint8Array};
+use serde_json::Value;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+
+use crate::services::logging::{log_frame_compressed, log_frame_undecodable};
+
+/// Whether to compress outgoing frames, set at build time with
+/// `COMPRESS_FRAMES` once the server understands the scheme. Compressed frames
+/// from the server are read either way.
+const ENABLED: bool = option_env!("COMPRESS_FRAMES").is_some();
+
+/// Frames shorter than this are sent plain; compressing them saves little.
+const MIN_FRAME_BYTES: usize = 4 * 1024;
+
+/// Frame field flagging a compressed frame. Its `data` is then the base64 of
+/// the gzipped UTF-8 text; every other field is left as is.
+const FLAG: &str = "compression";
+const FORMAT: &str = "gzip";
+
+/// Compresses the `data` of a large outgoing frame, or returns the frame
+/// unchanged when compression is off, unsupported or wouldn't make it smaller.
+pub async fn compress_frame(frame: String) -> String {
+    if !ENABLED || frame.len() < MIN_FRAME_BYTES || !supported() {
+        return frame;
+    }
+    let mut value: Value = match serde_json::from_str(&frame) {
+        Ok(value) => value,
+        Err(_) => return frame,
+    };
+    let data = match value.get("data").and_then(Value::as_str) {
+        Some(data) => data.to_string(),
+        None => return frame,
+    };
+    match compress(&data).await {
+        Ok(compressed) if compressed.len() < data.len() => {
+            value["data"] = compressed.into();
+            value[FLAG] = FORMAT.into();
+            let compressed = value.to_string();
+            log_frame_compressed(frame.len(), compressed.len());
+            compressed
+        }
+        Ok(_) => frame,
+        Err(e) => {
+            log::warn!(target: "ws", "event=compress_failed error={:?}", e);
+            frame
+        }
+    }
+}
+
+/// Restores the `data` of a compressed incoming frame, passing everything
+/// else through untouched, including frames that aren't JSON objects. `None`
+/// if a compressed frame can't be decompressed, e.g. because the browser
+/// lacks `DecompressionStream`.
+pub async fn decompress_frame(frame: String) -> Option<String> {
+    // Skip parsing the vast majority of frames, which are plain.
+    if !frame.contains(FLAG) {
+        return Some(frame);
+    }
+    let mut value: Value = match serde_json::from_str(&frame) {
+        Ok(value) => value,
+        Err(_) => return Some(frame),
+    };
+    let object = match value.as_object_mut() {
+        Some(object) => object,
+        None => return Some(frame),
+    };
+    match object.get(FLAG).and_then(Value::as_str) {
+        None => return Some(frame),
+        Some(FORMAT) if supported() => {}
+        Some(format) => {
+            log_frame_undecodable(format);
+            return None;
+        }
+    }
+    object.remove(FLAG);
+    let data = object.get("data").and_then(Value::as_str)?;
+    match decompress(data).await {
+        Ok(data) => {
+            object.insert("data".to_string(), data.into());
+            Some(value.to_string())
+        }
+        Err(e) => {
+            log::warn!(target: "ws", "event=decompress_failed error={:?}", e);
+            None
+        }
+    }
+}
+
+/// Whether the browser has `CompressionStream` and `DecompressionStream`.
+/// `web-sys` has no bindings for them at the version used here, so they are
+/// looked up dynamically.
+fn supported() -> bool {
+    constructor("CompressionStream").is_ok() && constructor("DecompressionStream").is_ok()
+}
+
+/// Gzips `text` and encodes the result as base64.
+async fn compress(text: &str) -> Result<String, JsValue> {
+    let bytes = pipe_through("CompressionStream", text.as_bytes()).await?;
+    // `btoa` takes a string of Latin-1 characters, one per byte.
+    let binary: String = bytes.into_iter().map(char::from).collect();
+    gloo::utils::window().btoa(&binary)
+}
+
+/// Reverses [`compress`].
+async fn decompress(data: &str) -> Result<String, JsValue> {
+    let binary = gloo::utils::window().atob(data)?;
+    let bytes: Vec<u8> = binary.chars().map(|c| c as u8).collect();
+    let bytes = pipe_through("DecompressionStream", &bytes).await?;
+    String::from_utf8(bytes).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// Runs `bytes` through a new `CompressionStream` or `DecompressionStream`,
+/// named by `stream`, and collects the output.
+async fn pipe_through(stream: &str, bytes: &[u8]) -> Result<Vec<u8>, JsValue> {
+    let transform = Reflect::construct(&constructor(stream)?, &Array::of1(&FORMAT.into()))?;
+    let parts = Array::of1(&Uint8Array::from(bytes));
+    let blob = Reflect::construct(&constructor("Blob")?, &Array::of1(&parts))?;
+    let readable = call(&blob, "stream", &Array::new())?;
+    let output = call(&readable, "pipeThrough", &Array::of1(&transform))?;
+    let response = Reflect::construct(&constructor("Response")?, &Array::of1(&output))?;
+    let buffer: Promise = call(&response, "arrayBuffer", &Array::new())?.dyn_into()?;
+    Ok(Uint8Array::new(&JsFuture::from(buffer).await?).to_vec())
+}
+
+fn constructor(name: &str) -> Result<Function, JsValue> {
+    Reflect::get(&js_sys::global(), &name.into())?.dyn_into()
+}
+
+fn call(target: &JsValue, method: &str, args: &Array) -> Result<JsValue, JsValue> {
+    let method: Function = Reflect::get(target, &method.into())?.dyn_into()?;
+    Reflect::apply(&method, target, args)
+}
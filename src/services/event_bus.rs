@@ -2,9 +2,23 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 use yew_agent::{Agent, AgentLink, Context, HandlerId};
 
+use crate::services::toast::ToastMessage;
+use crate::services::websocket::ConnectionState;
+
 #[derive(Serialize, Deserialize, Debug)]
 pub enum Request {
     EventBusMsg(String),
+    Connection(ConnectionState),
+    Toast(ToastMessage),
+}
+
+/// Broadcast to every subscriber of the bus.
+#[derive(Clone, Debug)]
+pub enum Event {
+    /// A raw frame received from the server.
+    Frame(String),
+    Connection(ConnectionState),
+    Toast(ToastMessage),
 }
 
 pub struct EventBus {
@@ -16,7 +30,7 @@ impl Agent for EventBus {
     type Reach = Context<Self>;
     type Message = ();
     type Input = Request;
-    type Output = String;
+    type Output = Event;
 
     fn create(link: AgentLink<Self>) -> Self {
         Self {
@@ -28,12 +42,13 @@ impl Agent for EventBus {
     fn update(&mut self, _msg: Self::Message) {}
 
     fn handle_input(&mut self, msg: Self::Input, _id: HandlerId) {
-        match msg {
-            Request::EventBusMsg(s) => {
-                for sub in self.subscribers.iter() {
-                    self.link.respond(*sub, s.clone())
-                }
-            }
+        let event = match msg {
+            Request::EventBusMsg(s) => Event::Frame(s),
+            Request::Connection(state) => Event::Connection(state),
+            Request::Toast(toast) => Event::Toast(toast),
+        };
+        for sub in self.subscribers.iter() {
+            self.link.respond(*sub, event.clone())
         }
     }
 
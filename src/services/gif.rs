@@ -0,0 +1,57 @@
+use std::collections::HashMap;
+
+use reqwasm::http::Request;
+use serde::Deserialize;
+
+/// Tenor API key, read at build time from `TENOR_API_KEY`. The GIF picker is
+/// hidden when it isn't set.
+pub const API_KEY: Option<&str> = option_env!("TENOR_API_KEY");
+
+const SEARCH_URL: &str = "https://tenor.googleapis.com/v2/search";
+const RESULT_LIMIT: u32 = 24;
+
+#[derive(Clone, PartialEq)]
+pub struct Gif {
+    /// Small rendition shown in the picker grid.
+    pub preview: String,
+    /// Full-size `.gif` URL that gets sent as the message.
+    pub url: String,
+}
+
+#[derive(Deserialize)]
+struct SearchResponse {
+    results: Vec<SearchResult>,
+}
+
+#[derive(Deserialize)]
+struct SearchResult {
+    media_formats: HashMap<String, MediaFormat>,
+}
+
+#[derive(Deserialize)]
+struct MediaFormat {
+    url: String,
+}
+
+pub async fn search(key: &str, query: &str) -> Result<Vec<Gif>, reqwasm::Error> {
+    let url = format!(
+        "{}?q={}&key={}&limit={}&media_filter=gif,tinygif",
+        SEARCH_URL,
+        js_sys::encode_uri_component(query),
+        key,
+        RESULT_LIMIT
+    );
+    let response: SearchResponse = Request::get(&url).send().await?.json().await?;
+    Ok(response
+        .results
+        .into_iter()
+        .filter_map(|mut r| {
+            let url = r.media_formats.remove("gif")?.url;
+            let preview = r
+                .media_formats
+                .remove("tinygif")
+                .map_or(url.clone(), |m| m.url);
+            Some(Gif { preview, url })
+        })
+        .collect())
+}
@@ -0,0 +1,72 @@
+use std::fmt::Debug;
+use std::str::FromStr;
+
+use log::Level;
+use web_sys::UrlSearchParams;
+
+/// Installs the console logger.
+///
+/// The level defaults to `debug` in dev builds and `info` in release builds and
+/// can be overridden with a `?log=<level>` query parameter, e.g. `?log=trace`.
+pub fn init() {
+    let level = level_from_query().unwrap_or(if cfg!(debug_assertions) {
+        Level::Debug
+    } else {
+        Level::Info
+    });
+    wasm_logger::init(wasm_logger::Config::new(level));
+}
+
+fn level_from_query() -> Option<Level> {
+    let search = gloo::utils::window().location().search().ok()?;
+    let params = UrlSearchParams::new_with_str(&search).ok()?;
+    Level::from_str(&params.get("log")?).ok()
+}
+
+pub fn log_frame_sent(frame: &str) {
+    log::debug!(target: "ws", "direction=out frame={}", frame);
+}
+
+pub fn log_frame_received(frame: &str) {
+    log::debug!(target: "ws", "direction=in frame={}", frame);
+}
+
+pub fn log_unparsable_frame(e: impl Debug) {
+    log::warn!(target: "ws", "event=unparsable_frame error={:?}", e);
+}
+
+pub fn log_unknown_frame(version: u8) {
+    log::warn!(target: "ws", "event=unknown_frame_skipped version={}", version);
+}
+
+pub fn log_send_error(e: impl Debug) {
+    log::error!(target: "ws", "event=send_failed error={:?}", e);
+}
+
+pub fn log_socket_error(e: impl Debug) {
+    log::error!(target: "ws", "event=socket_error error={:?}", e);
+}
+
+pub fn log_socket_closed() {
+    log::info!(target: "ws", "event=closed");
+}
+
+pub fn log_duplicate_dropped(from: &str) {
+    log::debug!(target: "chat", "event=duplicate_dropped from={}", from);
+}
+
+pub fn log_sequence_gap(expected: u64, received: u64) {
+    log::warn!(target: "chat", "event=sequence_gap expected={} received={}", expected, received);
+}
+
+pub fn log_sends_expired(count: usize) {
+    log::warn!(target: "chat", "event=sends_expired count={}", count);
+}
+
+pub fn log_frame_compressed(before: usize, after: usize) {
+    log::debug!(target: "ws", "event=compressed bytes_before={} bytes_after={}", before, after);
+}
+
+pub fn log_frame_undecodable(compression: &str) {
+    log::warn!(target: "ws", "event=frame_dropped compression={}", compression);
+}
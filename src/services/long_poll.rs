@@ -0,0 +1,197 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+
+use futures::channel::mpsc::{Receiver, Sender};
+use futures::future::{select, Either};
+use futures::{pin_mut, StreamExt};
+use gloo::timers::future::{IntervalStream, TimeoutFuture};
+use reqwasm::http::Request as HttpRequest;
+use wasm_bindgen_futures::spawn_local;
+use yew_agent::Dispatched;
+
+use crate::services::event_bus::{EventBus, Request};
+use crate::services::logging::{
+    log_frame_received, log_frame_sent, log_send_error, log_socket_closed, log_socket_error,
+};
+use crate::services::transport::{encode_frames, Transport};
+use crate::services::websocket::{
+    auth_frame, keepalive_frame, redact_auth, ConnectionState, WebSocketMessage, WsError,
+    KEEPALIVE_INTERVAL_MS, OUTBOUND_QUEUE,
+};
+
+const POLL_URL: &str = "http://127.0.0.1:8080/poll";
+
+/// Polls that may fail in a row before the server is considered gone.
+const MAX_POLL_FAILURES: u32 = 5;
+const RETRY_DELAY_MS: u32 = 2_000;
+
+/// Fallback for networks that block WebSockets, speaking the same frames over
+/// plain HTTP within a session named by the client:
+///
+/// - `POST /poll/<session>` with a JSON array of frames sends them. The first
+///   one carries the auth and handshake frames and opens the session.
+/// - `GET /poll/<session>` answers with a JSON array of the frames waiting for
+///   the client, holding the request open until there are some or the server
+///   times out with an empty array.
+///
+/// Reports progress on the [`EventBus`] like
+/// [`WebsocketService`](crate::services::websocket::WebsocketService). Dropping
+/// the service stops polling.
+pub struct LongPollService {
+    tx: Sender<String>,
+    handshake: Rc<RefCell<Vec<String>>>,
+}
+
+impl LongPollService {
+    pub fn new(token: Option<String>) -> Self {
+        let (tx, rx) = futures::channel::mpsc::channel::<String>(OUTBOUND_QUEUE);
+        let handshake = Rc::new(RefCell::new(vec![]));
+        let session = format!(
+            "{:x}{:x}",
+            js_sys::Date::now() as u64,
+            (js_sys::Math::random() * u32::MAX as f64) as u32
+        );
+        spawn_local(run(
+            format!("{}/{}", POLL_URL, session),
+            rx,
+            token.map(auth_frame),
+            handshake.clone(),
+        ));
+        Self { tx, handshake }
+    }
+}
+
+impl Transport for LongPollService {
+    /// Only takes effect if set before the session opens.
+    fn set_handshake(&self, messages: &[WebSocketMessage]) -> Result<(), WsError> {
+        *self.handshake.borrow_mut() = encode_frames(messages)?;
+        Ok(())
+    }
+
+    fn send(&self, message: &WebSocketMessage) -> Result<(), WsError> {
+        let frame =
+            serde_json::to_string(message).map_err(|e| WsError::Serialize(e.to_string()))?;
+        self.tx.clone().try_send(frame).map_err(|e| {
+            if e.is_disconnected() {
+                WsError::NotConnected
+            } else {
+                WsError::SendFailed
+            }
+        })
+    }
+
+    fn close(&mut self) {
+        self.tx.close_channel();
+    }
+}
+
+async fn run(
+    url: String,
+    mut rx: Receiver<String>,
+    auth: Option<String>,
+    handshake: Rc<RefCell<Vec<String>>>,
+) {
+    let mut event_bus = EventBus::dispatcher();
+    event_bus.send(Request::Connection(ConnectionState::Connecting));
+
+    let hello: Vec<String> = auth
+        .into_iter()
+        .chain(handshake.borrow().iter().cloned())
+        .collect();
+    if let Err(e) = post(&url, &hello).await {
+        log_socket_error(e.to_string());
+        event_bus.send(Request::Connection(ConnectionState::Unreachable));
+        return;
+    }
+    event_bus.send(Request::Connection(ConnectionState::Connected));
+
+    let outbound = async {
+        let mut keepalive = IntervalStream::new(KEEPALIVE_INTERVAL_MS);
+        loop {
+            let frame = match select(rx.next(), keepalive.next()).await {
+                Either::Left((Some(frame), _)) => frame,
+                Either::Left((None, _)) => break,
+                Either::Right(_) => keepalive_frame(),
+            };
+            // Whatever else queued up meanwhile goes in the same request.
+            let mut frames = vec![frame];
+            while let Ok(Some(frame)) = rx.try_next() {
+                frames.push(frame);
+            }
+            if let Err(e) = post(&url, &frames).await {
+                log_send_error(e.to_string());
+            }
+        }
+    };
+
+    let inbound = async {
+        let mut failures = 0;
+        while failures < MAX_POLL_FAILURES {
+            match poll(&url).await {
+                Ok(frames) => {
+                    failures = 0;
+                    for frame in frames {
+                        log_frame_received(&frame);
+                        event_bus.send(Request::EventBusMsg(frame));
+                    }
+                }
+                Err(e) => {
+                    failures += 1;
+                    log_socket_error(e.to_string());
+                    TimeoutFuture::new(RETRY_DELAY_MS).await;
+                }
+            }
+        }
+        log_socket_closed();
+        event_bus.send(Request::Connection(ConnectionState::Disconnected(None)));
+    };
+
+    pin_mut!(outbound, inbound);
+    select(outbound, inbound).await;
+}
+
+enum PollError {
+    Http(reqwasm::Error),
+    /// The server answered with a status other than 2xx.
+    Status(u16),
+}
+
+impl fmt::Display for PollError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PollError::Http(e) => write!(f, "{}", e),
+            PollError::Status(status) => write!(f, "HTTP status {}", status),
+        }
+    }
+}
+
+impl From<reqwasm::Error> for PollError {
+    fn from(e: reqwasm::Error) -> Self {
+        PollError::Http(e)
+    }
+}
+
+async fn post(url: &str, frames: &[String]) -> Result<(), PollError> {
+    for frame in frames {
+        log_frame_sent(&redact_auth(frame));
+    }
+    let body = serde_json::to_string(frames).unwrap();
+    let response = HttpRequest::post(url)
+        .header("Content-Type", "application/json")
+        .body(body)
+        .send()
+        .await?;
+    if !response.ok() {
+        return Err(PollError::Status(response.status()));
+    }
+    Ok(())
+}
+
+async fn poll(url: &str) -> Result<Vec<String>, PollError> {
+    let response = HttpRequest::get(url).send().await?;
+    if !response.ok() {
+        return Err(PollError::Status(response.status()));
+    }
+    Ok(response.json().await?)
+}
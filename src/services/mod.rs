@@ -1,2 +1,13 @@
-pub mod websocket;
+pub mod auth;
+pub mod avatar;
+pub mod clipboard;
+pub mod compression;
 pub mod event_bus;
+pub mod gif;
+pub mod logging;
+pub mod long_poll;
+pub mod preferences;
+pub mod sound;
+pub mod toast;
+pub mod transport;
+pub mod websocket;
@@ -0,0 +1,464 @@
+use std::fmt;
+use std::str::FromStr;
+
+use gloo::storage::{LocalStorage, Storage};
+use serde::{Deserialize, Serialize};
+
+use crate::services::avatar::DEFAULT_AVATAR_SET;
+use crate::services::websocket::ReconnectStrategy;
+
+const STORAGE_KEY: &str = "yewchat.preferences";
+
+/// Longest idle timeout that can be set, a day.
+pub const MAX_IDLE_TIMEOUT_MINS: u32 = 24 * 60;
+
+/// User settings persisted in `localStorage`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Preferences {
+    /// Minutes without activity before the logout warning appears, at most
+    /// [`MAX_IDLE_TIMEOUT_MINS`]; `0` disables the auto-logout.
+    pub idle_timeout_mins: u32,
+    /// Play a sound for messages that arrive while the window isn't focused.
+    pub notification_sound: bool,
+    /// Silence notifications until turned off again.
+    pub do_not_disturb: bool,
+    /// Silence notifications at the same time every day.
+    pub quiet_hours: QuietHours,
+    pub avatar_size: AvatarSize,
+    pub avatar_shape: AvatarShape,
+    /// Robohash set our own avatar is drawn from; shared with other users.
+    pub avatar_set: u8,
+    pub time_format: TimeFormat,
+    /// Browser spellcheck in the composer.
+    pub spellcheck: bool,
+    pub send_key: SendKey,
+    /// Fade messages out as they get older.
+    pub fade_old_messages: bool,
+    /// Longest gap between consecutive messages from one sender that still
+    /// keeps them under the same time header.
+    pub group_window_secs: u32,
+    /// Applies from the next connection on.
+    pub reconnect: ReconnectStrategy,
+    /// Where the message list starts once history arrives after a reload.
+    /// Reconnecting never moves it.
+    pub scroll_on_load: ScrollOnLoad,
+    /// Canned replies inserted with `/template <name>`.
+    pub templates: Vec<Template>,
+}
+
+impl Default for Preferences {
+    fn default() -> Self {
+        Self {
+            idle_timeout_mins: 15,
+            notification_sound: true,
+            do_not_disturb: false,
+            quiet_hours: QuietHours {
+                enabled: false,
+                start: TimeOfDay(22 * 60),
+                end: TimeOfDay(7 * 60),
+            },
+            avatar_size: AvatarSize::Md,
+            avatar_shape: AvatarShape::Circle,
+            avatar_set: DEFAULT_AVATAR_SET,
+            time_format: TimeFormat::Locale,
+            spellcheck: true,
+            send_key: SendKey::Enter,
+            fade_old_messages: true,
+            group_window_secs: 300,
+            reconnect: ReconnectStrategy::default(),
+            scroll_on_load: ScrollOnLoad::Latest,
+            templates: vec![
+                Template {
+                    name: "greeting".to_string(),
+                    text: "Hi! Thanks for reaching out. How can I help?".to_string(),
+                },
+                Template {
+                    name: "thanks".to_string(),
+                    text: "Thanks for your patience! Let me know if there's anything else."
+                        .to_string(),
+                },
+            ],
+        }
+    }
+}
+
+impl Preferences {
+    pub fn load() -> Self {
+        LocalStorage::get(STORAGE_KEY).unwrap_or_default()
+    }
+
+    /// Whether notifications are silenced at local time `now`.
+    pub fn notifications_muted(&self, now: TimeOfDay) -> bool {
+        self.do_not_disturb || self.quiet_hours.contains(now)
+    }
+
+    pub fn save(&self) {
+        if let Err(e) = LocalStorage::set(STORAGE_KEY, self) {
+            log::error!("failed to save preferences: {:?}", e);
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AvatarSize {
+    Sm,
+    Md,
+    Lg,
+}
+
+impl AvatarSize {
+    pub const ALL: [AvatarSize; 3] = [AvatarSize::Sm, AvatarSize::Md, AvatarSize::Lg];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AvatarSize::Sm => "sm",
+            AvatarSize::Md => "md",
+            AvatarSize::Lg => "lg",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            AvatarSize::Sm => "Small",
+            AvatarSize::Md => "Medium",
+            AvatarSize::Lg => "Large",
+        }
+    }
+
+    /// Tailwind size classes for the roster; medium is 48px.
+    pub fn roster_class(self) -> &'static str {
+        match self {
+            AvatarSize::Sm => "w-8 h-8",
+            AvatarSize::Md => "w-12 h-12",
+            AvatarSize::Lg => "w-16 h-16",
+        }
+    }
+
+    /// Tailwind size classes next to message bubbles; medium is 32px.
+    pub fn message_class(self) -> &'static str {
+        match self {
+            AvatarSize::Sm => "w-6 h-6",
+            AvatarSize::Md => "w-8 h-8",
+            AvatarSize::Lg => "w-12 h-12",
+        }
+    }
+}
+
+impl FromStr for AvatarSize {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        AvatarSize::ALL
+            .into_iter()
+            .find(|size| size.as_str() == s)
+            .ok_or(())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AvatarShape {
+    Circle,
+    Rounded,
+}
+
+impl AvatarShape {
+    pub const ALL: [AvatarShape; 2] = [AvatarShape::Circle, AvatarShape::Rounded];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AvatarShape::Circle => "circle",
+            AvatarShape::Rounded => "rounded",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            AvatarShape::Circle => "Circle",
+            AvatarShape::Rounded => "Rounded square",
+        }
+    }
+
+    pub fn class(self) -> &'static str {
+        match self {
+            AvatarShape::Circle => "rounded-full",
+            AvatarShape::Rounded => "rounded-lg",
+        }
+    }
+}
+
+impl FromStr for AvatarShape {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        AvatarShape::ALL
+            .into_iter()
+            .find(|shape| shape.as_str() == s)
+            .ok_or(())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TimeFormat {
+    /// Whatever the browser's locale uses.
+    Locale,
+    #[serde(rename = "12h")]
+    TwelveHour,
+    #[serde(rename = "24h")]
+    TwentyFourHour,
+}
+
+impl TimeFormat {
+    pub const ALL: [TimeFormat; 3] = [
+        TimeFormat::Locale,
+        TimeFormat::TwelveHour,
+        TimeFormat::TwentyFourHour,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TimeFormat::Locale => "locale",
+            TimeFormat::TwelveHour => "12h",
+            TimeFormat::TwentyFourHour => "24h",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            TimeFormat::Locale => "Locale default",
+            TimeFormat::TwelveHour => "12-hour",
+            TimeFormat::TwentyFourHour => "24-hour",
+        }
+    }
+
+    /// Value of the `hour12` option of `Intl.DateTimeFormat`; `None` leaves
+    /// it to the locale.
+    pub fn hour12(self) -> Option<bool> {
+        match self {
+            TimeFormat::Locale => None,
+            TimeFormat::TwelveHour => Some(true),
+            TimeFormat::TwentyFourHour => Some(false),
+        }
+    }
+}
+
+impl FromStr for TimeFormat {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        TimeFormat::ALL
+            .into_iter()
+            .find(|f| f.as_str() == s)
+            .ok_or(())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScrollOnLoad {
+    /// Jump to the newest message.
+    Latest,
+    /// Stay where the list was scrolled to before the reload.
+    Restore,
+}
+
+impl ScrollOnLoad {
+    pub const ALL: [ScrollOnLoad; 2] = [ScrollOnLoad::Latest, ScrollOnLoad::Restore];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ScrollOnLoad::Latest => "latest",
+            ScrollOnLoad::Restore => "restore",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ScrollOnLoad::Latest => "Jump to the newest message",
+            ScrollOnLoad::Restore => "Stay where I was",
+        }
+    }
+}
+
+impl FromStr for ScrollOnLoad {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ScrollOnLoad::ALL
+            .into_iter()
+            .find(|scroll| scroll.as_str() == s)
+            .ok_or(())
+    }
+}
+
+/// A named snippet of message text.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Template {
+    pub name: String,
+    pub text: String,
+}
+
+/// Minutes after local midnight, written `HH:MM` like the value of an
+/// `<input type="time">`.
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct TimeOfDay(pub u16);
+
+impl TimeOfDay {
+    pub fn now() -> Self {
+        let date = js_sys::Date::new_0();
+        TimeOfDay((date.get_hours() * 60 + date.get_minutes()) as u16)
+    }
+}
+
+impl fmt::Display for TimeOfDay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02}:{:02}", self.0 / 60, self.0 % 60)
+    }
+}
+
+impl FromStr for TimeOfDay {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (hours, minutes) = s.split_once(':').ok_or(())?;
+        let hours: u16 = hours.parse().map_err(|_| ())?;
+        let minutes: u16 = minutes.parse().map_err(|_| ())?;
+        if hours < 24 && minutes < 60 {
+            Ok(TimeOfDay(hours * 60 + minutes))
+        } else {
+            Err(())
+        }
+    }
+}
+
+/// Daily window during which notifications are silenced. It runs from
+/// `start` up to `end`, past midnight when `end` comes first; the times are
+/// kept while it's disabled.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct QuietHours {
+    pub enabled: bool,
+    pub start: TimeOfDay,
+    pub end: TimeOfDay,
+}
+
+impl QuietHours {
+    pub fn contains(&self, now: TimeOfDay) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        if self.start <= self.end {
+            self.start <= now && now < self.end
+        } else {
+            now >= self.start || now < self.end
+        }
+    }
+}
+
+/// Which Enter press sends the message; the others insert a newline.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SendKey {
+    /// Enter sends, Shift+Enter inserts a newline.
+    Enter,
+    /// Ctrl+Enter (Cmd+Enter on macOS) sends, Enter inserts a newline.
+    CtrlEnter,
+}
+
+impl SendKey {
+    pub const ALL: [SendKey; 2] = [SendKey::Enter, SendKey::CtrlEnter];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SendKey::Enter => "enter",
+            SendKey::CtrlEnter => "ctrl-enter",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SendKey::Enter => "Enter",
+            SendKey::CtrlEnter => "Ctrl+Enter",
+        }
+    }
+
+    /// Whether an Enter press with these modifiers sends the message.
+    pub fn sends(self, ctrl: bool, shift: bool) -> bool {
+        match self {
+            SendKey::Enter => !shift,
+            SendKey::CtrlEnter => ctrl,
+        }
+    }
+}
+
+impl FromStr for SendKey {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        SendKey::ALL.into_iter().find(|k| k.as_str() == s).ok_or(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{QuietHours, SendKey, TimeFormat, TimeOfDay};
+
+    #[test]
+    fn time_format_hour12() {
+        assert_eq!(TimeFormat::Locale.hour12(), None);
+        assert_eq!(TimeFormat::TwelveHour.hour12(), Some(true));
+        assert_eq!(TimeFormat::TwentyFourHour.hour12(), Some(false));
+    }
+
+    #[test]
+    fn time_format_round_trips() {
+        for format in TimeFormat::ALL {
+            assert_eq!(format.as_str().parse(), Ok(format));
+            let json = serde_json::to_string(&format).unwrap();
+            assert_eq!(json, format!("\"{}\"", format.as_str()));
+        }
+    }
+
+    #[test]
+    fn send_key_modifiers() {
+        assert!(SendKey::Enter.sends(false, false));
+        assert!(!SendKey::Enter.sends(false, true));
+        assert!(!SendKey::CtrlEnter.sends(false, false));
+        assert!(SendKey::CtrlEnter.sends(true, false));
+        for key in SendKey::ALL {
+            assert_eq!(key.as_str().parse(), Ok(key));
+            assert_eq!(
+                serde_json::to_string(&key).unwrap(),
+                format!("\"{}\"", key.as_str())
+            );
+        }
+    }
+
+    #[test]
+    fn quiet_hours_wrap_past_midnight() {
+        let at = |time: &str| time.parse::<TimeOfDay>().unwrap();
+        let mut night = QuietHours {
+            enabled: true,
+            start: at("22:00"),
+            end: at("07:00"),
+        };
+        assert!(night.contains(at("23:30")));
+        assert!(night.contains(at("00:00")));
+        assert!(!night.contains(at("07:00")));
+        assert!(!night.contains(at("12:00")));
+        night.enabled = false;
+        assert!(!night.contains(at("23:30")));
+
+        let meeting = QuietHours {
+            enabled: true,
+            start: at("09:00"),
+            end: at("10:30"),
+        };
+        assert!(meeting.contains(at("09:00")));
+        assert!(!meeting.contains(at("10:30")));
+        assert_eq!(at("07:05").to_string(), "07:05");
+        assert!("24:00".parse::<TimeOfDay>().is_err());
+    }
+}
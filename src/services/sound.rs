@@ -0,0 +1,101 @@
+use gloo::file::futures::read_as_data_url;
+use gloo::file::File;
+use gloo::storage::{LocalStorage, Storage};
+use std::cell::RefCell;
+use wasm_bindgen::JsValue;
+use wasm_bindgen_futures::{spawn_local, JsFuture};
+use web_sys::{AudioContext, HtmlAudioElement, OscillatorType};
+
+const STORAGE_KEY: &str = "yewchat.notification-sound";
+
+/// Largest audio file accepted as a custom sound. It is stored as a data URI
+/// in `localStorage`, which is typically capped at 5 MB per origin.
+pub const MAX_SOUND_BYTES: u64 = 512 * 1024;
+
+thread_local! {
+    // Browsers cap how many contexts a page may open, so the chime reuses one.
+    static AUDIO_CONTEXT: RefCell<Option<AudioContext>> = const { RefCell::new(None) };
+}
+
+/// Plays the custom notification sound, or the built-in chime when none is
+/// set or the custom one fails to play.
+pub fn play_notification() {
+    match LocalStorage::get::<String>(STORAGE_KEY) {
+        Ok(uri) => spawn_local(async move {
+            if let Err(e) = play_uri(&uri).await {
+                log::error!("custom notification sound failed: {:?}", e);
+                play_default();
+            }
+        }),
+        Err(_) => play_default(),
+    }
+}
+
+async fn play_uri(uri: &str) -> Result<(), JsValue> {
+    let audio = HtmlAudioElement::new_with_src(uri)?;
+    JsFuture::from(audio.play()?).await?;
+    Ok(())
+}
+
+/// A short two-tone chime synthesized with Web Audio.
+fn play_default() {
+    let result = (|| -> Result<(), JsValue> {
+        let ctx = audio_context()?;
+        let now = ctx.current_time();
+        let gain = ctx.create_gain()?;
+        gain.gain().set_value_at_time(0.2, now)?;
+        gain.gain()
+            .exponential_ramp_to_value_at_time(0.001, now + 0.3)?;
+        gain.connect_with_audio_node(&ctx.destination())?;
+        for (i, freq) in [880.0, 1320.0].into_iter().enumerate() {
+            let osc = ctx.create_oscillator()?;
+            osc.set_type(OscillatorType::Sine);
+            osc.frequency().set_value(freq);
+            osc.connect_with_audio_node(&gain)?;
+            let start = now + i as f64 * 0.12;
+            osc.start_with_when(start)?;
+            osc.stop_with_when(start + 0.15)?;
+        }
+        Ok(())
+    })();
+    if let Err(e) = result {
+        log::error!("notification chime failed: {:?}", e);
+    }
+}
+
+fn audio_context() -> Result<AudioContext, JsValue> {
+    AUDIO_CONTEXT.with(|cell| {
+        let mut cell = cell.borrow_mut();
+        if let Some(ctx) = cell.as_ref() {
+            return Ok(ctx.clone());
+        }
+        let ctx = AudioContext::new()?;
+        *cell = Some(ctx.clone());
+        Ok(ctx)
+    })
+}
+
+pub fn has_custom_sound() -> bool {
+    LocalStorage::get::<String>(STORAGE_KEY).is_ok()
+}
+
+/// Validates and stores `file` as the notification sound.
+pub async fn set_custom_sound(file: File) -> Result<(), String> {
+    if !file.raw_mime_type().starts_with("audio/") {
+        return Err("That file isn't an audio file.".into());
+    }
+    if file.size() > MAX_SOUND_BYTES {
+        return Err(format!(
+            "Sounds must be under {} KB.",
+            MAX_SOUND_BYTES / 1024
+        ));
+    }
+    let uri = read_as_data_url(&file)
+        .await
+        .map_err(|e| format!("Couldn't read the file: {}", e))?;
+    LocalStorage::set(STORAGE_KEY, uri).map_err(|e| format!("Couldn't save the sound: {}", e))
+}
+
+pub fn clear_custom_sound() {
+    LocalStorage::delete(STORAGE_KEY);
+}
@@ -0,0 +1,32 @@
+use serde::{Deserialize, Serialize};
+use yew_agent::Dispatched;
+
+use crate::services::event_bus::{EventBus, Request};
+
+/// How long a toast stays up unless given another duration.
+pub const DEFAULT_TTL_MS: u32 = 4_000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ToastLevel {
+    Info,
+    Success,
+    Error,
+}
+
+/// A transient notification shown by the `ToastHub`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ToastMessage {
+    pub text: String,
+    pub level: ToastLevel,
+    /// Milliseconds before it dismisses itself.
+    pub ttl_ms: u32,
+}
+
+/// Shows a toast from anywhere in the app.
+pub fn show_toast(text: impl Into<String>, level: ToastLevel) {
+    EventBus::dispatcher().send(Request::Toast(ToastMessage {
+        text: text.into(),
+        level,
+        ttl_ms: DEFAULT_TTL_MS,
+    }));
+}
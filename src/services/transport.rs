@@ -0,0 +1,25 @@
+use crate::services::websocket::{WebSocketMessage, WsError};
+
+/// A way of exchanging frames with the chat server. Whatever the transport,
+/// inbound frames and connection changes are reported on the
+/// [`EventBus`](crate::services::event_bus::EventBus).
+pub trait Transport {
+    /// Sets the frames that introduce this client, e.g. `register` and
+    /// `join`. They are sent as soon as the connection opens, before any
+    /// queued frames, so the server always knows who is talking.
+    fn set_handshake(&self, messages: &[WebSocketMessage]) -> Result<(), WsError>;
+
+    /// Queues a frame for sending.
+    fn send(&self, message: &WebSocketMessage) -> Result<(), WsError>;
+
+    /// Closes the connection for good, without reconnecting. Later sends fail.
+    fn close(&mut self);
+}
+
+pub fn encode_frames(messages: &[WebSocketMessage]) -> Result<Vec<String>, WsError> {
+    messages
+        .iter()
+        .map(serde_json::to_string)
+        .collect::<Result<_, _>>()
+        .map_err(|e| WsError::Serialize(e.to_string()))
+}
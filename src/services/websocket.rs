@@ -1,53 +1,697 @@
-use futures::{channel::mpsc::Sender, SinkExt, StreamExt};
-use reqwasm::websocket::{futures::WebSocket, Message};
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+use std::str::FromStr;
+use std::task::Poll;
+
+use futures::channel::mpsc::{Receiver, Sender};
+use futures::future::{poll_fn, select, Either};
+use futures::{pin_mut, SinkExt, StreamExt};
+use gloo::timers::future::{IntervalStream, TimeoutFuture};
+use reqwasm::websocket::{futures::WebSocket, Message, State, WebSocketError};
+use serde::{Deserialize, Serialize};
 
 use wasm_bindgen_futures::spawn_local;
-use yew_agent::Dispatched;
+use yew_agent::{Dispatched, Dispatcher};
 
+use crate::services::compression::{compress_frame, decompress_frame};
 use crate::services::event_bus::{EventBus, Request};
+use crate::services::logging::{
+    log_frame_received, log_frame_sent, log_send_error, log_socket_closed, log_socket_error,
+};
+use crate::services::transport::{encode_frames, Transport};
+
+const SERVER_URL: &str = "ws://127.0.0.1:8080";
+
+/// Capacity of the outbound queue; further sends fail with
+/// [`WsError::SendFailed`] until it drains.
+pub const OUTBOUND_QUEUE: usize = 1000;
+
+/// Reconnect attempts made after losing the connection before giving up.
+pub const MAX_RECONNECT_ATTEMPTS: u32 = 10;
+const BACKOFF_BASE_MS: u32 = 500;
+const BACKOFF_CAP_MS: u32 = 30_000;
+
+/// Interval between `keepalive` frames while connected.
+///
+/// This is application-level liveness for the roster, independent of the
+/// socket itself: a connection can stay open while the tab is frozen or the
+/// client is wedged. The server is expected to remove a user from the roster
+/// (and broadcast the updated `users` frame) after missing three consecutive
+/// keepalives, i.e. after roughly 90 seconds of silence.
+pub const KEEPALIVE_INTERVAL_MS: u32 = 30_000;
+
+/// Protocol version sent with every frame. Frames from servers that predate
+/// versioning carry none and read as `0`.
+pub const PROTOCOL_VERSION: u8 = 1;
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MsgTypes {
+    Users,
+    Register,
+    Message,
+    Join,
+    Presence,
+    Keepalive,
+    Pin,
+    Unpin,
+    Profile,
+    Typing,
+    Auth,
+    /// The server is removing us; `data` holds the reason. A close follows.
+    Kicked,
+    /// Flags a message for moderators, with `{"message_id", "reason"}` as JSON
+    /// `data`. The server acknowledges with the message id as `data`.
+    Report,
+    /// Asks the server to post a poll, with `{"question", "options"}` as JSON
+    /// `data`. It comes back as a `message` whose metadata is
+    /// `{"type": "poll", "title": question, "options"}`, the message id
+    /// identifying the poll.
+    Poll,
+    /// A vote on a poll, with `{"poll_id", "option"}` as JSON `data` and a
+    /// null `option` taking the vote back. The server relays votes to
+    /// everyone with the voter's `user_id` added.
+    Vote,
+    /// Settings of the room named in `room`, with `{"read_only"}` as JSON
+    /// `data`, sent when we join it. Only admins may post in read-only rooms.
+    Room,
+    /// A message's text was changed, with `{"message_id", "message",
+    /// "edited_at"}` as JSON `data`. `edited_at` is in milliseconds since the
+    /// epoch and may be left out.
+    Edit,
+    /// A type this client doesn't know, e.g. from a newer server. Never sent.
+    #[serde(other, skip_serializing)]
+    Unknown,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebSocketMessage {
+    pub message_type: MsgTypes,
+    pub data_array: Option<Vec<String>>,
+    pub data: Option<String>,
+    pub room: Option<String>,
+    #[serde(default)]
+    pub version: u8,
+    /// On a `message`, asks the server to relay it live without keeping it
+    /// in history.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub ephemeral: bool,
+    /// On a `message`, the id of the thread root it replies to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reply_to: Option<String>,
+    /// On a `message`, the name of who originally sent it, for one forwarded
+    /// from another room.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub forwarded_from: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ConnectionState {
+    Connecting,
+    Connected,
+    Reconnecting {
+        attempt: u32,
+        max: u32,
+    },
+    /// The very first connection attempt failed, so the server is most
+    /// likely not running. Like `Disconnected`, a new service has to be
+    /// created to try again.
+    Unreachable,
+    /// Reconnecting gave up, or the server closed the connection in a way
+    /// that rules it out; a new service has to be created to try again. Holds
+    /// the last close frame received, if any.
+    Disconnected(Option<CloseReason>),
+}
+
+/// How well the open connection is doing, judged from recent round trips and
+/// failed sends. The UI sheds non-essential work when it isn't `Good`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ConnectionQuality {
+    Good,
+    Degraded,
+    Poor,
+}
+
+impl ConnectionQuality {
+    /// Rates a connection from round-trip times in milliseconds and the
+    /// outcome of recent sends (`true` for failed). Without samples it's
+    /// assumed to be good.
+    pub fn assess(round_trips_ms: &[f64], failed_sends: &[bool]) -> Self {
+        let average_rtt = if round_trips_ms.is_empty() {
+            0.0
+        } else {
+            round_trips_ms.iter().sum::<f64>() / round_trips_ms.len() as f64
+        };
+        let failure_rate = if failed_sends.is_empty() {
+            0.0
+        } else {
+            failed_sends.iter().filter(|&&failed| failed).count() as f64 / failed_sends.len() as f64
+        };
+        if average_rtt > 1_500.0 || failure_rate > 0.25 {
+            Self::Poor
+        } else if average_rtt > 500.0 || failure_rate > 0.05 {
+            Self::Degraded
+        } else {
+            Self::Good
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Good => "Good",
+            Self::Degraded => "Slow",
+            Self::Poor => "Poor",
+        }
+    }
+}
+
+/// Close code and reason sent by the server when it closed the socket.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CloseReason {
+    pub code: u16,
+    pub reason: String,
+}
+
+impl CloseReason {
+    /// The server closed on purpose and didn't ask the client to go away.
+    pub const NORMAL: u16 = 1000;
+    pub const GOING_AWAY: u16 = 1001;
+    /// No close frame arrived, e.g. the network dropped.
+    pub const ABNORMAL: u16 = 1006;
+    pub const POLICY_VIOLATION: u16 = 1008;
+    pub const INTERNAL_ERROR: u16 = 1011;
+    /// Application code: another user already registered this name.
+    pub const USERNAME_TAKEN: u16 = 4001;
+    /// Application code: a moderator removed this user from the chat.
+    pub const KICKED: u16 = 4002;
+    /// Application code: this user has been banned.
+    pub const BANNED: u16 = 4003;
+    /// Application code: the auth token is missing, invalid or expired.
+    pub const UNAUTHORIZED: u16 = 4401;
+    /// Application code: the server has reached its user limit.
+    pub const SERVER_FULL: u16 = 4503;
+
+    /// Whether reconnecting could succeed. Closes that are deliberate
+    /// decisions about this user would only be repeated.
+    pub fn should_reconnect(&self) -> bool {
+        !matches!(
+            self.code,
+            Self::POLICY_VIOLATION
+                | Self::USERNAME_TAKEN
+                | Self::KICKED
+                | Self::BANNED
+                | Self::UNAUTHORIZED
+                | Self::SERVER_FULL
+        )
+    }
+
+    /// User-facing explanation, including the server's reason when it gave
+    /// one.
+    pub fn describe(&self) -> String {
+        let summary = match self.code {
+            Self::NORMAL => "The server closed the connection.".to_string(),
+            Self::GOING_AWAY => "The server is shutting down or restarting.".to_string(),
+            Self::ABNORMAL => "The connection to the server was lost.".to_string(),
+            Self::POLICY_VIOLATION => {
+                "You were disconnected for breaking the server's rules.".to_string()
+            }
+            Self::INTERNAL_ERROR => "The server ran into an error.".to_string(),
+            Self::USERNAME_TAKEN => "That username is already taken.".to_string(),
+            Self::KICKED => "You were removed from the chat.".to_string(),
+            Self::BANNED => "You have been banned from this server.".to_string(),
+            Self::UNAUTHORIZED => "Your session has expired. Please log in again.".to_string(),
+            Self::SERVER_FULL => "The chat is full.".to_string(),
+            code => format!("The connection was closed (code {}).", code),
+        };
+        if self.reason.is_empty() {
+            summary
+        } else {
+            format!("{} Reason: {}", summary, self.reason)
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum WsError {
+    /// The outbound queue is full.
+    SendFailed,
+    /// The frame couldn't be encoded as JSON.
+    Serialize(String),
+    /// The service has shut down without the server giving a reason.
+    NotConnected,
+    /// The server closed the connection and reconnecting gave up.
+    Closed(CloseReason),
+}
+
+impl fmt::Display for WsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WsError::SendFailed => write!(f, "outbound queue is full"),
+            WsError::Serialize(e) => write!(f, "failed to serialize frame: {}", e),
+            WsError::NotConnected => write!(f, "not connected"),
+            WsError::Closed(close) => write!(
+                f,
+                "closed by server: code {}, reason {:?}",
+                close.code, close.reason
+            ),
+        }
+    }
+}
+
+/// How the connection is re-established after it drops.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum ReconnectStrategy {
+    /// Retry straight away every time.
+    Immediate,
+    /// Wait `base_ms` before the first retry, doubling for each further one
+    /// up to `cap_ms`.
+    Backoff { base_ms: u32, cap_ms: u32 },
+    /// Never retry; the user reconnects from the disconnected banner.
+    Manual,
+}
+
+impl Default for ReconnectStrategy {
+    fn default() -> Self {
+        ReconnectStrategy::Backoff {
+            base_ms: BACKOFF_BASE_MS,
+            cap_ms: BACKOFF_CAP_MS,
+        }
+    }
+}
+
+impl ReconnectStrategy {
+    /// One of each kind, with default settings.
+    pub const ALL: [ReconnectStrategy; 3] = [
+        ReconnectStrategy::Immediate,
+        ReconnectStrategy::Backoff {
+            base_ms: BACKOFF_BASE_MS,
+            cap_ms: BACKOFF_CAP_MS,
+        },
+        ReconnectStrategy::Manual,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ReconnectStrategy::Immediate => "immediate",
+            ReconnectStrategy::Backoff { .. } => "backoff",
+            ReconnectStrategy::Manual => "manual",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ReconnectStrategy::Immediate => "Immediately",
+            ReconnectStrategy::Backoff { .. } => "With increasing delays",
+            ReconnectStrategy::Manual => "Only when I ask",
+        }
+    }
+
+    /// Milliseconds to wait before reconnect attempt `attempt`, counting from
+    /// 1, or `None` to not reconnect at all.
+    pub fn delay_ms(self, attempt: u32) -> Option<u32> {
+        match self {
+            ReconnectStrategy::Immediate => Some(0),
+            ReconnectStrategy::Backoff { base_ms, cap_ms } => Some(
+                base_ms
+                    .saturating_mul(1 << attempt.saturating_sub(1).min(16))
+                    .min(cap_ms),
+            ),
+            ReconnectStrategy::Manual => None,
+        }
+    }
+}
+
+/// Kinds are matched by name; backoff gets the default delays.
+impl FromStr for ReconnectStrategy {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ReconnectStrategy::ALL
+            .into_iter()
+            .find(|r| r.as_str() == s)
+            .ok_or(())
+    }
+}
 
+/// Connection to the chat server. Reconnects as the [`ReconnectStrategy`] says
+/// when the socket drops, reporting progress on the [`EventBus`]. Frames sent while
+/// disconnected are queued and flushed once the connection is back, after the
+/// handshake, as is a frame whose write failed when the socket broke. Dropping
+/// the service closes the socket.
 pub struct WebsocketService {
-    pub tx: Sender<String>,
+    tx: Sender<String>,
+    /// Frames sent first on every new connection, see [`Self::set_handshake`].
+    handshake: Rc<RefCell<Vec<String>>>,
+    /// Code and reason of the most recent close frame from the server.
+    last_close: Rc<RefCell<Option<CloseReason>>>,
+}
+
+/// How a single connection ended.
+enum Ended {
+    /// Every sender was dropped, so the service is shutting down.
+    Dropped,
+    /// The socket closed or failed to open.
+    Closed { opened: bool },
 }
 
 impl WebsocketService {
-    pub fn new() -> Self {
-        let ws = WebSocket::open("ws://127.0.0.1:8080").unwrap();
+    /// Connects to the server. With a `token`, every connection starts with an
+    /// `auth` frame carrying it as `data`, ahead of the handshake; servers
+    /// that reject it close with [`CloseReason::UNAUTHORIZED`].
+    pub fn new(token: Option<String>, strategy: ReconnectStrategy) -> Self {
+        let (in_tx, in_rx) = futures::channel::mpsc::channel::<String>(OUTBOUND_QUEUE);
+        let last_close = Rc::new(RefCell::new(None));
+        let auth = token.map(auth_frame);
+        let handshake = Rc::new(RefCell::new(vec![]));
+        spawn_local(run(
+            in_rx,
+            auth,
+            handshake.clone(),
+            last_close.clone(),
+            strategy,
+        ));
+        Self {
+            tx: in_tx,
+            handshake,
+            last_close,
+        }
+    }
+}
+
+impl Transport for WebsocketService {
+    /// Handshake frames are sent again after every reconnect.
+    fn set_handshake(&self, messages: &[WebSocketMessage]) -> Result<(), WsError> {
+        *self.handshake.borrow_mut() = encode_frames(messages)?;
+        Ok(())
+    }
+
+    /// Frames queued while reconnecting are sent once the connection is back.
+    fn send(&self, message: &WebSocketMessage) -> Result<(), WsError> {
+        let frame =
+            serde_json::to_string(message).map_err(|e| WsError::Serialize(e.to_string()))?;
+        self.tx.clone().try_send(frame).map_err(|e| {
+            if !e.is_disconnected() {
+                return WsError::SendFailed;
+            }
+            match self.last_close.borrow().clone() {
+                Some(close) => WsError::Closed(close),
+                None => WsError::NotConnected,
+            }
+        })
+    }
 
-        let (mut write, mut read) = ws.split();
+    fn close(&mut self) {
+        self.tx.close_channel();
+    }
+}
+
+async fn run(
+    mut in_rx: Receiver<String>,
+    auth: Option<String>,
+    handshake: Rc<RefCell<Vec<String>>>,
+    last_close: Rc<RefCell<Option<CloseReason>>>,
+    strategy: ReconnectStrategy,
+) {
+    let mut event_bus = EventBus::dispatcher();
+    event_bus.send(Request::Connection(ConnectionState::Connecting));
+
+    let mut attempt = 0;
+    let mut ever_opened = false;
+    let unsent = RefCell::new(None);
+    loop {
+        let ended = connect(
+            &mut in_rx,
+            &mut event_bus,
+            auth.as_deref(),
+            &handshake,
+            &last_close,
+            &unsent,
+        )
+        .await;
+        match ended {
+            Ended::Dropped => return,
+            Ended::Closed { opened: true } => {
+                ever_opened = true;
+                attempt = 1;
+            }
+            Ended::Closed { opened: false } if !ever_opened => {
+                event_bus.send(Request::Connection(ConnectionState::Unreachable));
+                return;
+            }
+            Ended::Closed { opened: false } => attempt += 1,
+        }
+        let close = last_close.borrow().clone();
+        let give_up = close.as_ref().is_some_and(|c| !c.should_reconnect());
+        let delay = strategy.delay_ms(attempt);
+        let delay = match delay {
+            Some(delay) if !give_up && attempt <= MAX_RECONNECT_ATTEMPTS => delay,
+            _ => {
+                event_bus.send(Request::Connection(ConnectionState::Disconnected(close)));
+                return;
+            }
+        };
+        event_bus.send(Request::Connection(ConnectionState::Reconnecting {
+            attempt,
+            max: MAX_RECONNECT_ATTEMPTS,
+        }));
+        TimeoutFuture::new(delay).await;
+    }
+}
+
+async fn connect(
+    in_rx: &mut Receiver<String>,
+    event_bus: &mut Dispatcher<EventBus>,
+    auth: Option<&str>,
+    handshake: &RefCell<Vec<String>>,
+    last_close: &RefCell<Option<CloseReason>>,
+    unsent: &RefCell<Option<String>>,
+) -> Ended {
+    let mut ws = match WebSocket::open(SERVER_URL) {
+        Ok(ws) => ws,
+        Err(e) => {
+            log_socket_error(e);
+            return Ended::Closed { opened: false };
+        }
+    };
+
+    // The sink becomes ready once the socket leaves the connecting state,
+    // while a failed attempt shows up as an error on the stream.
+    let settled = poll_fn(|cx| {
+        if ws.poll_ready_unpin(cx).is_ready() {
+            return Poll::Ready(true);
+        }
+        match ws.poll_next_unpin(cx) {
+            Poll::Ready(Some(Err(e))) => {
+                log_socket_error(e);
+                Poll::Ready(false)
+            }
+            Poll::Ready(_) => Poll::Ready(false),
+            Poll::Pending => Poll::Pending,
+        }
+    })
+    .await;
+    if !settled || !matches!(ws.state(), State::Open) {
+        return Ended::Closed { opened: false };
+    }
+    // A close from an earlier connection says nothing about this one.
+    *last_close.borrow_mut() = None;
+    event_bus.send(Request::Connection(ConnectionState::Connected));
 
-        let (in_tx, mut in_rx) = futures::channel::mpsc::channel::<String>(1000);
-        let mut event_bus = EventBus::dispatcher();
+    let (mut write, mut read) = ws.split();
+    let hello: Vec<String> = auth
+        .map(str::to_string)
+        .into_iter()
+        .chain(handshake.borrow().iter().cloned())
+        .collect();
 
-        spawn_local(async move {
-            while let Some(s) = in_rx.next().await {
-                log::debug!("got event from channel! {}", s);
-                write.send(Message::Text(s)).await.unwrap();
+    let outbound = async move {
+        for s in hello {
+            log_frame_sent(&redact_auth(&s));
+            if let Err(e) = write.send(Message::Text(s)).await {
+                log_send_error(e);
+            }
+        }
+        let mut keepalive = IntervalStream::new(KEEPALIVE_INTERVAL_MS);
+        let mut retry = unsent.take();
+        loop {
+            let frame = match retry.take() {
+                Some(frame) => frame,
+                None => {
+                    let s = match select(in_rx.next(), keepalive.next()).await {
+                        Either::Left((Some(s), _)) => s,
+                        Either::Left((None, _)) => break,
+                        Either::Right(_) => keepalive_frame(),
+                    };
+                    log_frame_sent(&s);
+                    compress_frame(s).await
+                }
+            };
+            if let Err(e) = write.send(Message::Text(frame.clone())).await {
+                log_send_error(e);
+                // The socket is gone; hold on to the frame for the next one.
+                unsent.replace(Some(frame));
+                return Ended::Closed { opened: true };
             }
-        });
+        }
+        Ended::Dropped
+    };
 
-        spawn_local(async move {
-            while let Some(msg) = read.next().await {
-                match msg {
-                    Ok(Message::Text(data)) => {
-                        log::debug!("from websocket: {}", data);
+    let inbound = async move {
+        while let Some(msg) = read.next().await {
+            match msg {
+                Ok(Message::Text(data)) => {
+                    if let Some(data) = decompress_frame(data).await {
+                        log_frame_received(&data);
                         event_bus.send(Request::EventBusMsg(data));
                     }
-                    Ok(Message::Bytes(b)) => {
-                        let decoded = std::str::from_utf8(&b);
-                        if let Ok(val) = decoded {
-                            log::debug!("from websocket: {}", val);
-                            event_bus.send(Request::EventBusMsg(val.into()));
+                }
+                Ok(Message::Bytes(b)) => {
+                    let decoded = String::from_utf8(b).ok();
+                    if let Some(val) = decoded {
+                        if let Some(val) = decompress_frame(val).await {
+                            log_frame_received(&val);
+                            event_bus.send(Request::EventBusMsg(val));
                         }
                     }
-                    Err(e) => {
-                        log::error!("ws: {:?}", e)
-                    }
                 }
+                Err(WebSocketError::ConnectionClose(e)) => {
+                    *last_close.borrow_mut() = Some(CloseReason {
+                        code: e.code,
+                        reason: e.reason,
+                    });
+                }
+                Err(e) => log_socket_error(e),
             }
-            log::debug!("WebSocket Closed");
-        });
+        }
+        log_socket_closed();
+        Ended::Closed { opened: true }
+    };
+
+    // Whichever side finishes first drops both halves of the socket, which
+    // closes the connection.
+    pin_mut!(outbound, inbound);
+    match select(outbound, inbound).await {
+        Either::Left((ended, _)) | Either::Right((ended, _)) => ended,
+    }
+}
+
+/// First frame of every connection when logged in with a token.
+pub fn auth_frame(token: String) -> String {
+    serde_json::to_string(&WebSocketMessage {
+        message_type: MsgTypes::Auth,
+        data: Some(token),
+        data_array: None,
+        room: None,
+        version: PROTOCOL_VERSION,
+        ephemeral: false,
+        reply_to: None,
+        forwarded_from: None,
+    })
+    .unwrap()
+}
+
+/// `frame` with the token of an auth frame masked, for logging.
+pub fn redact_auth(frame: &str) -> Cow<'_, str> {
+    match serde_json::from_str::<WebSocketMessage>(frame) {
+        Ok(mut msg) if matches!(msg.message_type, MsgTypes::Auth) => {
+            msg.data = Some("<redacted>".to_string());
+            Cow::Owned(serde_json::to_string(&msg).unwrap())
+        }
+        _ => Cow::Borrowed(frame),
+    }
+}
+
+pub fn keepalive_frame() -> String {
+    serde_json::to_string(&WebSocketMessage {
+        message_type: MsgTypes::Keepalive,
+        data_array: None,
+        data: None,
+        room: None,
+        version: PROTOCOL_VERSION,
+        ephemeral: false,
+        reply_to: None,
+        forwarded_from: None,
+    })
+    .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        auth_frame, keepalive_frame, redact_auth, ConnectionQuality, MsgTypes, ReconnectStrategy,
+        WebSocketMessage,
+    };
+
+    #[test]
+    fn assesses_connection_quality() {
+        assert_eq!(ConnectionQuality::assess(&[], &[]), ConnectionQuality::Good);
+        assert_eq!(
+            ConnectionQuality::assess(&[80.0, 120.0], &[false; 10]),
+            ConnectionQuality::Good
+        );
+        assert_eq!(
+            ConnectionQuality::assess(&[600.0, 800.0], &[]),
+            ConnectionQuality::Degraded
+        );
+        assert_eq!(
+            ConnectionQuality::assess(&[100.0], &[true, false, false]),
+            ConnectionQuality::Poor
+        );
+        assert_eq!(
+            ConnectionQuality::assess(&[2_000.0], &[false]),
+            ConnectionQuality::Poor
+        );
+    }
+
+    #[test]
+    fn reads_unversioned_and_unknown_frames() {
+        let msg: WebSocketMessage =
+            serde_json::from_str(r#"{"messageType":"users","dataArray":["a"]}"#).unwrap();
+        assert!(matches!(msg.message_type, MsgTypes::Users));
+        assert_eq!(msg.version, 0);
+
+        let msg: WebSocketMessage = serde_json::from_str(
+            r#"{"messageType":"reaction","data":"x","version":2,"emoji":"👍"}"#,
+        )
+        .unwrap();
+        assert!(matches!(msg.message_type, MsgTypes::Unknown));
+        assert_eq!(msg.version, 2);
+    }
+
+    #[test]
+    fn reconnect_delays() {
+        let backoff = ReconnectStrategy::Backoff {
+            base_ms: 500,
+            cap_ms: 3_000,
+        };
+        let delays: Vec<_> = (1..=5).map(|attempt| backoff.delay_ms(attempt)).collect();
+        assert_eq!(
+            delays,
+            [
+                Some(500),
+                Some(1_000),
+                Some(2_000),
+                Some(3_000),
+                Some(3_000)
+            ]
+        );
+        assert_eq!(ReconnectStrategy::Immediate.delay_ms(3), Some(0));
+        assert_eq!(ReconnectStrategy::Manual.delay_ms(1), None);
+        for strategy in ReconnectStrategy::ALL {
+            assert_eq!(strategy.as_str().parse(), Ok(strategy));
+        }
+    }
 
-        Self { tx: in_tx }
+    #[test]
+    fn auth_token_is_redacted_for_logging() {
+        let redacted = redact_auth(&auth_frame("secret-token".to_string())).into_owned();
+        assert!(!redacted.contains("secret-token"));
+        assert!(redacted.contains("<redacted>"));
+        let keepalive = keepalive_frame();
+        assert_eq!(redact_auth(&keepalive), keepalive);
     }
 }